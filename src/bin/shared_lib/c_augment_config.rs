@@ -0,0 +1,45 @@
+﻿use serde::{Deserialize, Serialize};
+
+// Random distortions applied to every training batch (never to validation / test data).
+// Ranges are symmetric and sampled per image, 0 switches a step off.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AugmentConfig {
+    pub enabled: bool,
+
+    // affine: ±degrees, ±relative scale, ±shear degrees, ±share of the image side
+    pub rotation_deg: f64,
+    pub scale: f64,
+    pub shear_deg: f64,
+    pub translate: f64,
+
+    // elastic distortion: max displacement in pixels, smoothness of the field in pixels
+    pub elastic_alpha: f64,
+    pub elastic_sigma: f64,
+
+    // chance of a 3x3 dilation (thicker strokes) or erosion (thinner strokes), split evenly
+    pub morphology_prob: f64,
+
+    // std of gaussian pixel noise, pixels are 0..1
+    pub noise_std: f64,
+}
+
+impl Default for AugmentConfig {
+    fn default() -> Self {
+        Self{
+            enabled: true,
+
+            rotation_deg: 10.0,
+            scale: 0.1,
+            shear_deg: 5.0,
+            translate: 0.08,
+
+            elastic_alpha: 0.0,
+            elastic_sigma: 4.0,
+
+            morphology_prob: 0.2,
+
+            noise_std: 0.0,
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,44 @@
+﻿use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+// One line of a run's metrics.csv / metrics.jsonl. Epoch rows leave test_acc empty, the final
+// evaluation of a stage is logged as its own "test" / "finetune_test" row.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+pub struct EpochMetrics {
+    // "train", "test", "finetune", "finetune_test"
+    pub stage: String,
+    pub epoch: usize,
+    pub loss: Option<f64>,
+    pub lr: Option<f64>,
+    pub train_acc: Option<f64>,
+    pub val_acc: Option<f64>,
+    pub test_acc: Option<f64>,
+    // wall time of the epoch
+    pub seconds: f64,
+}
+
+impl EpochMetrics {
+    pub fn is_test(&self) -> bool {
+        self.stage.ends_with("test")
+    }
+
+    pub fn load_jsonl(path: impl AsRef<Path>) -> io::Result<Vec<Self>> {
+        let path = path.as_ref();
+        let reader = BufReader::new(fs::File::open(path)?);
+
+        let mut rows = Vec::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let row = serde_json::from_str(&line).map_err(|e| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}: {e}", path.display(), i + 1))
+            })?;
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+}
\ No newline at end of file
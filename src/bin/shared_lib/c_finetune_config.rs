@@ -0,0 +1,55 @@
+﻿use serde::{Deserialize, Serialize};
+
+// How uneven label folders are evened out for training (the held-out part is left as it is)
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ClassBalance {
+    None,
+    // cross-entropy weighted by inverse class frequency
+    Weighted,
+    // random repeats of smaller classes up to the largest one
+    Oversample,
+}
+
+// Second training stage on the samples collected in the solver GUI
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct FinetuneConfig {
+    pub enabled: bool,
+    pub data_dir: String,
+    pub epochs: usize,
+    pub lr: f64,
+    pub batch_size: i64,
+    // share of every batch replayed from the MNIST training set, keeps the model from forgetting it
+    pub replay_ratio: f64,
+    // share of mydata kept out of training to measure the effect of fine-tuning
+    pub holdout_fraction: f64,
+    // written next to the base model, which is never overwritten
+    pub out_path: String,
+    // variable prefixes kept fixed, e.g. "l1" or "c1"
+    pub frozen_layers: Vec<String>,
+    // redraw samples from their saved strokes with the current rasterizer
+    pub rerasterize: bool,
+    pub balance: ClassBalance,
+    // random subset of larger classes, applied before `balance` (0 = keep all)
+    pub max_per_class: usize,
+}
+
+impl Default for FinetuneConfig {
+    fn default() -> Self {
+        Self{
+            enabled: true,
+            data_dir: "mydata/".to_string(),
+            epochs: 20,
+            lr: 1e-4,
+            batch_size: 32,
+            replay_ratio: 0.5,
+            holdout_fraction: 0.2,
+            out_path: "models/mnist_finetuned.ot".to_string(),
+            frozen_layers: vec![],
+            rerasterize: false,
+            balance: ClassBalance::None,
+            max_per_class: 0,
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,125 @@
+﻿use std::fs;
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::shared_lib::c_preprocess_config::PreprocessConfig;
+use crate::shared_lib::c_trainer_config::{ModelArchitecture, TrainerConfig};
+
+pub const MANIFEST_VERSION: u32 = 1;
+
+// Everything needed to rebuild the network before loading the weights
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct ModelSpec {
+    pub architecture: ModelArchitecture,
+    pub image_dim: i64,
+    pub hidden: i64,
+    pub labels: i64,
+}
+
+impl ModelSpec {
+    pub fn from_config(config: &TrainerConfig) -> Self {
+        Self{
+            architecture: config.architecture,
+            image_dim: config.image_dim,
+            hidden: config.hidden,
+            labels: config.labels,
+        }
+    }
+}
+
+// model input = (pixel_u8 * scale - mean) / std
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct InputNormalization {
+    pub scale: f32,
+    pub mean: f32,
+    pub std: f32,
+}
+
+impl Default for InputNormalization {
+    fn default() -> Self {
+        Self{
+            scale: 1.0 / 255.0,
+            mean: 0.0,
+            std: 1.0,
+        }
+    }
+}
+
+// Sidecar JSON stored next to the weights: models/mnist.ot -> models/mnist.json
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ModelManifest {
+    pub format_version: u32,
+    pub model: ModelSpec,
+    pub normalization: InputNormalization,
+    // how the solver has to turn drawings into input; missing = the original pipeline
+    #[serde(default)]
+    pub preprocess: PreprocessConfig,
+    pub label_names: Vec<String>,
+    pub epochs: usize,
+    #[serde(default)]
+    pub val_accuracy: Option<f64>,
+    pub test_accuracy: Option<f64>,
+    // RNG seed of the run that produced the weights
+    #[serde(default)]
+    pub seed: Option<u64>,
+    // trainer run folder with the metrics log and evaluation reports
+    #[serde(default)]
+    pub run_dir: Option<String>,
+
+    // set on fine-tuned models: base weights they started from
+    #[serde(default)]
+    pub finetuned_from: Option<String>,
+    #[serde(default)]
+    pub finetune_epochs: usize,
+}
+
+impl ModelManifest {
+    pub fn from_config(config: &TrainerConfig) -> Self {
+        Self{
+            format_version: MANIFEST_VERSION,
+            model: ModelSpec::from_config(config),
+            normalization: InputNormalization::default(),
+            preprocess: config.preprocess,
+            label_names: (0..config.labels).map(|l| l.to_string()).collect(),
+            epochs: 0,
+            val_accuracy: None,
+            test_accuracy: None,
+            seed: Some(config.seed),
+            run_dir: None,
+
+            finetuned_from: None,
+            finetune_epochs: 0,
+        }
+    }
+
+    pub fn path_for(weights: impl AsRef<Path>) -> PathBuf {
+        weights.as_ref().with_extension("json")
+    }
+
+    pub fn load_for(weights: impl AsRef<Path>) -> io::Result<Self> {
+        let path = Self::path_for(weights);
+        let file = fs::File::open(&path)?;
+
+        let manifest: ModelManifest = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {e}", path.display())))?;
+
+        if manifest.format_version > MANIFEST_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: unsupported manifest version {}", path.display(), manifest.format_version),
+            ));
+        }
+        Ok(manifest)
+    }
+
+    pub fn save_for(&self, weights: impl AsRef<Path>) -> io::Result<()> {
+        let path = Self::path_for(weights);
+        let mut w = BufWriter::new(fs::File::create(&path)?);
+        serde_json::to_writer_pretty(&mut w, self)?;
+        Ok(())
+    }
+
+    pub fn normalize(&self, pixel01: f32) -> f32 {
+        (pixel01 * 255.0 * self.normalization.scale - self.normalization.mean) / self.normalization.std
+    }
+}
\ No newline at end of file
@@ -0,0 +1,53 @@
+﻿use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum OptimizerKind {
+    Sgd,
+    Adam,
+    AdamW,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LrSchedule {
+    Constant,
+    // lr * gamma every `every_epochs` epochs
+    Step { every_epochs: usize, gamma: f64 },
+    // lr -> min_lr along half a cosine over the whole run
+    Cosine { min_lr: f64 },
+    // lr * factor after `patience` epochs without a validation improvement
+    Plateau { patience: usize, factor: f64, min_lr: f64 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct OptimizerSettings {
+    pub kind: OptimizerKind,
+    pub lr: f64,
+    pub weight_decay: f64,
+
+    // SGD only
+    pub momentum: f64,
+    pub nesterov: bool,
+
+    pub schedule: LrSchedule,
+    // linear ramp from ~0 to the scheduled lr over the first steps (0 = off)
+    pub warmup_steps: usize,
+}
+
+impl Default for OptimizerSettings {
+    fn default() -> Self {
+        Self{
+            kind: OptimizerKind::Adam,
+            lr: 1e-3,
+            weight_decay: 0.0,
+
+            momentum: 0.9,
+            nesterov: false,
+
+            schedule: LrSchedule::Constant,
+            warmup_steps: 0,
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,62 @@
+﻿use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Brush {
+    // solid disks, the 28x28 image only gets gray from downsampling
+    Hard,
+    // soft edge over a share of the radius, closer to MNIST's scanned pen strokes
+    AntiAliased,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Centering {
+    // square bounding box plus `padding` scaled to target_size², in the middle of 28x28
+    BoundingBox,
+    // like MNIST: longer side scaled to target_size keeping the aspect ratio, center of mass moved to the middle
+    CenterOfMass,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Resize {
+    Bilinear,
+    // box filter, keeps thin strokes when shrinking a lot (center_of_mass always uses it)
+    Area,
+}
+
+// Strokes on the canvas -> 28x28 model input. Stored in the model manifest so the solver feeds a model
+// exactly what it was trained on, and with every sample so it is known how its pixels were made.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(default)]
+pub struct PreprocessConfig {
+    pub brush: Brush,
+    // fast parts of a stroke (points far apart) get thinner, like a pen
+    pub velocity_width: bool,
+    // side of the square buffer strokes are drawn on before cropping
+    pub hi_size: i32,
+    // buffer values above this count as ink for the bounding box
+    pub bbox_threshold: f32,
+    pub centering: Centering,
+    // margin around the bounding box, share of its longer side (bounding_box only)
+    pub padding: f32,
+    // the digit is scaled into a target_size² box inside 28x28
+    pub target_size: i32,
+    pub resize: Resize,
+}
+
+impl Default for PreprocessConfig {
+    fn default() -> Self {
+        Self{
+            brush: Brush::Hard,
+            velocity_width: false,
+            hi_size: 280,
+            bbox_threshold: 0.05,
+            centering: Centering::BoundingBox,
+            padding: 0.2,
+            target_size: 20,
+            resize: Resize::Bilinear,
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,229 @@
+﻿use std::fs;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::shared_lib::c_preprocess_config::PreprocessConfig;
+
+// 2: canvas rect stored, strokes can be re-rasterized
+pub const SAMPLE_VERSION: u32 = 2;
+pub const SAMPLE_SIDE: u32 = 28;
+
+// One pen stroke as drawn in the solver, points in canvas (screen) coordinates
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Stroke {
+    pub size: f32,
+    pub points: Vec<[f32; 2]>,
+}
+
+// The solver's drawing area in screen coordinates, strokes are rasterized relative to it
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct CanvasRect {
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+}
+
+impl CanvasRect {
+    pub fn width(&self) -> f32 {
+        self.max[0] - self.min[0]
+    }
+
+    pub fn height(&self) -> f32 {
+        self.max[1] - self.min[1]
+    }
+}
+
+// Sidecar JSON next to the pixels: mydata/3/1770238848714.bin -> mydata/3/1770238848714.json
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SampleMeta {
+    pub format_version: u32,
+    pub label: u8,
+    pub width: u32,
+    pub height: u32,
+    // unix time in ms
+    pub created_ms: u64,
+    pub brush_size: f32,
+    pub strokes: Vec<Stroke>,
+    // missing on version 1 samples
+    #[serde(default)]
+    pub canvas: Option<CanvasRect>,
+    // pipeline the pixels were made with, missing = the original one
+    #[serde(default, alias = "raster")]
+    pub preprocess: PreprocessConfig,
+
+    // what the loaded model said when the sample was saved
+    pub prediction: Option<i64>,
+    pub probs: Vec<f32>,
+    #[serde(default)]
+    pub model: String,
+
+    #[serde(default)]
+    pub author: String,
+}
+
+// A mydata sample: raw u8 pixels (.bin, row-major) plus metadata when it has a sidecar.
+// Legacy samples are a bare 784-byte .bin.
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub path: PathBuf,
+    // taken from the folder, mydata/<label>/
+    pub label: u8,
+    pub pixels: Vec<u8>,
+    pub meta: Option<SampleMeta>,
+}
+
+impl SampleMeta {
+    pub fn path_for(bin: impl AsRef<Path>) -> PathBuf {
+        bin.as_ref().with_extension("json")
+    }
+
+    // Strokes and the canvas they were drawn on, when the sample has both
+    pub fn recording(&self) -> Option<(&CanvasRect, &[Stroke])> {
+        let canvas = self.canvas.as_ref()?;
+        if self.strokes.is_empty() || canvas.width() <= 0.0 || canvas.height() <= 0.0 {
+            return None;
+        }
+        Some((canvas, &self.strokes))
+    }
+}
+
+impl Sample {
+    // Writes <dir>/<label>/<created_ms>.bin and its .json, returns the .bin path
+    pub fn save(dir: impl AsRef<Path>, pixels: &[u8], meta: &SampleMeta) -> io::Result<PathBuf> {
+        if pixels.len() != (meta.width * meta.height) as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} pixels for a {}x{} sample", pixels.len(), meta.width, meta.height),
+            ));
+        }
+
+        let dir = dir.as_ref().join(meta.label.to_string());
+        fs::create_dir_all(&dir)?;
+
+        // two saves in the same millisecond get a suffix
+        let path = free_path(&dir, &meta.created_ms.to_string());
+
+        fs::write(&path, pixels)?;
+        write_meta(&path, meta)?;
+        Ok(path)
+    }
+
+    // Moves the .bin and its .json to <root>/<label>/, relabels the metadata.
+    // Used for relabeling (same root) and for moving samples out of the set.
+    pub fn move_to(&mut self, root: impl AsRef<Path>, label: u8) -> io::Result<()> {
+        let dir = root.as_ref().join(label.to_string());
+        if self.path.parent() == Some(dir.as_path()) {
+            return Ok(());
+        }
+        fs::create_dir_all(&dir)?;
+
+        let stem = self.path.file_stem().and_then(|s| s.to_str()).unwrap_or("sample").to_string();
+        let target = free_path(&dir, &stem);
+        fs::rename(&self.path, &target)?;
+
+        let old_meta = SampleMeta::path_for(&self.path);
+        if let Some(meta) = self.meta.as_mut() {
+            meta.label = label;
+            write_meta(&target, meta)?;
+            fs::remove_file(&old_meta)?;
+        }
+
+        self.path = target;
+        self.label = label;
+        Ok(())
+    }
+
+    pub fn delete(&self) -> io::Result<()> {
+        fs::remove_file(&self.path)?;
+        let meta = SampleMeta::path_for(&self.path);
+        if meta.exists() {
+            fs::remove_file(meta)?;
+        }
+        Ok(())
+    }
+
+    // Pixels as 0..1 floats
+    pub fn pixels01(&self) -> Vec<f32> {
+        self.pixels.iter().map(|&b| b as f32 / 255.0).collect()
+    }
+
+    pub fn load(path: impl AsRef<Path>, label: u8) -> io::Result<Self> {
+        let path = path.as_ref();
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {msg}", path.display()));
+
+        let pixels = fs::read(path)?;
+
+        let meta_path = SampleMeta::path_for(path);
+        let meta = if meta_path.exists() {
+            let file = fs::File::open(&meta_path)?;
+            let meta: SampleMeta = serde_json::from_reader(BufReader::new(file))
+                .map_err(|e| invalid(format!("bad metadata: {e}")))?;
+            if meta.format_version > SAMPLE_VERSION {
+                return Err(invalid(format!("unsupported sample version {}", meta.format_version)));
+            }
+            Some(meta)
+        } else {
+            None
+        };
+
+        let (w, h) = meta.as_ref().map(|m| (m.width, m.height)).unwrap_or((SAMPLE_SIDE, SAMPLE_SIDE));
+        if pixels.len() != (w * h) as usize {
+            return Err(invalid(format!("{} bytes, expected {w}x{h} = {}", pixels.len(), w * h)));
+        }
+
+        Ok(Self{
+            path: path.to_path_buf(),
+            label,
+            pixels,
+            meta,
+        })
+    }
+}
+
+// <dir>/<stem>.bin, or <stem>-2.bin, -3, ... when taken
+fn free_path(dir: &Path, stem: &str) -> PathBuf {
+    let mut path = dir.join(format!("{stem}.bin"));
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("{stem}-{n}.bin"));
+        n += 1;
+    }
+    path
+}
+
+fn write_meta(bin: &Path, meta: &SampleMeta) -> io::Result<()> {
+    let mut w = BufWriter::new(fs::File::create(SampleMeta::path_for(bin))?);
+    serde_json::to_writer_pretty(&mut w, meta)?;
+    w.flush()
+}
+
+// Every sample under <dir>/<0..9>/, plus one message per file that was skipped or looks wrong
+pub fn load_samples(dir: impl AsRef<Path>) -> (Vec<Sample>, Vec<String>) {
+    let dir = dir.as_ref();
+    let mut samples = Vec::new();
+    let mut problems = Vec::new();
+
+    for label in 0..10u8 {
+        let Ok(rd) = fs::read_dir(dir.join(label.to_string())) else { continue; };
+
+        let mut paths: Vec<PathBuf> = rd
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().and_then(|s| s.to_str()) == Some("bin"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            match Sample::load(&path, label) {
+                Ok(s) => {
+                    if let Some(meta) = s.meta.as_ref().filter(|m| m.label != label) {
+                        problems.push(format!("{}: metadata says label {}, using the folder's {label}", path.display(), meta.label));
+                    }
+                    samples.push(s);
+                }
+                Err(e) => problems.push(e.to_string()),
+            }
+        }
+    }
+
+    (samples, problems)
+}
\ No newline at end of file
@@ -1,30 +1,75 @@
 ﻿use std::env;
 use std::io::{BufReader, BufWriter};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
+use crate::shared_lib::c_augment_config::AugmentConfig;
+use crate::shared_lib::c_finetune_config::FinetuneConfig;
+use crate::shared_lib::c_optimizer_settings::OptimizerSettings;
+use crate::shared_lib::c_preprocess_config::PreprocessConfig;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ModelArchitecture {
+    Mlp,
+    Cnn,
+}
 
 #[derive(Serialize, Deserialize)]
+#[serde(default)]
 pub struct TrainerConfig {
     pub epoch: usize,
+    pub batch_size: i64,
+    pub val_fraction: f64,
+    pub patience: usize,
+    // seeds libtorch (init, validation split, shuffling, augmentation) for reproducible runs
+    pub seed: u64,
+    pub optimizer: OptimizerSettings,
+    // resumable training state every N epochs (0 = off), see `trainer_app train --resume`
+    pub checkpoint_every: usize,
+    pub checkpoint_path: String,
     pub data_dir: String,
+    // IDX images stored column by column (EMNIST)
+    pub data_transposed: bool,
     pub out_path: String,
+    // every training run gets a timestamped folder in here
+    pub runs_dir: String,
 
+    pub architecture: ModelArchitecture,
     pub image_dim: i64,
     pub hidden: i64,
-    pub labels: i64
+    pub labels: i64,
+    // canvas -> 28x28 pipeline of models trained with this config (mydata re-rasterizing, solver input)
+    pub preprocess: PreprocessConfig,
 
+    // used by both the MNIST and the fine-tune stage
+    pub augment: AugmentConfig,
+    pub finetune: FinetuneConfig,
 }
 
 impl Default for TrainerConfig {
     fn default() -> Self {
         Self{
             epoch: 20,
+            batch_size: 128,
+            val_fraction: 0.1,
+            patience: 3,
+            seed: 42,
+            optimizer: OptimizerSettings::default(),
+            checkpoint_every: 1,
+            checkpoint_path: "models/mnist_train_state.ot".to_string(),
             data_dir: "data/".to_string(),
+            data_transposed: false,
             out_path: "models/mnist.ot".to_string(),
+            runs_dir: "runs/".to_string(),
 
+            architecture: ModelArchitecture::Mlp,
             image_dim: 784,
             hidden: 128,
             labels: 10,
+            preprocess: PreprocessConfig::default(),
+
+            augment: AugmentConfig::default(),
+            finetune: FinetuneConfig::default(),
         }
     }
 }
@@ -33,6 +78,11 @@ impl Default for TrainerConfig {
 impl TrainerConfig {
     pub fn new() -> Self {
         let file = env::current_dir().unwrap().join("config.json");
+        Self::load(&file)
+    }
+
+    pub fn load(file: &Path) -> Self {
+        let file = file.to_path_buf();
 
         if (!file.exists()) {
             let default = TrainerConfig::default();
@@ -49,6 +99,42 @@ impl TrainerConfig {
         return cfg
     }
 
+    // A file given explicitly (`--config`): it has to exist and parse, and is never written back
+    pub fn load_from(file: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let file_open = std::fs::File::open(file).map_err(|e| format!("{}: {e}", file.display()))?;
+        let cfg = serde_json::from_reader(BufReader::new(file_open)).map_err(|e| format!("{}: {e}", file.display()))?;
+        Ok(cfg)
+    }
+
+    // Sets one field for this run only, `key` can be nested: "optimizer.lr", "finetune.epochs".
+    // The value is parsed as JSON (numbers, bools, objects); for string fields it is taken as is.
+    pub fn apply_override(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let mut root = serde_json::to_value(&*self).map_err(|e| e.to_string())?;
+
+        let mut slot = &mut root;
+        for part in key.split('.') {
+            slot = slot
+                .get_mut(part)
+                .ok_or_else(|| format!("unknown config field `{key}`"))?;
+        }
+
+        *slot = match slot {
+            serde_json::Value::String(_) => serde_json::Value::String(value.to_string()),
+            _ => serde_json::from_str(value).unwrap_or_else(|_| serde_json::Value::String(value.to_string())),
+        };
+
+        *self = serde_json::from_value(root).map_err(|e| format!("--{key} {value}: {e}"))?;
+        Ok(())
+    }
+
+    // The fine-tuned model when there is one, the base model otherwise
+    pub fn inference_model_path(&self) -> &str {
+        if self.finetune.enabled && Path::new(&self.finetune.out_path).exists() {
+            return &self.finetune.out_path;
+        }
+        &self.out_path
+    }
+
     fn save(file: &PathBuf, data: TrainerConfig) -> TrainerConfig {
         
         let file = std::fs::File::create(&file).expect("Failed to create json file");
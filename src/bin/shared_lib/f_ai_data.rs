@@ -1,18 +1,77 @@
-﻿use tch::nn;
-use tch::nn::Module;
-use crate::shared_lib::c_trainer_config::TrainerConfig;
+﻿use std::io;
+use std::path::Path;
+use tch::{nn, Device};
+use crate::shared_lib::c_model_manifest::{ModelManifest, ModelSpec};
+use crate::shared_lib::c_preprocess_config::PreprocessConfig;
+use crate::shared_lib::c_trainer_config::{ModelArchitecture, TrainerConfig};
 
-pub fn build_model(vs: &nn::Path, cfg: &TrainerConfig) -> nn::Sequential {
+pub fn build_model(vs: &nn::Path, spec: &ModelSpec) -> nn::Sequential {
+    match spec.architecture {
+        ModelArchitecture::Mlp => build_model_mlp(vs, spec),
+        ModelArchitecture::Cnn => build_model_cnn(vs, spec),
+    }
+}
+
+// Rebuilds the network described by the manifest next to `weights` and loads them.
+// Checkpoints saved before manifests existed fall back to the model settings of `fallback`
+// and the original preprocessing.
+pub fn load_checkpoint(
+    weights: impl AsRef<Path>,
+    fallback: &TrainerConfig,
+    device: Device,
+) -> Result<(nn::VarStore, nn::Sequential, ModelManifest), Box<dyn std::error::Error>> {
+    let weights = weights.as_ref();
+
+    let manifest = match ModelManifest::load_for(weights) {
+        Ok(m) => m,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            println!("no manifest for {}, using config.json model settings", weights.display());
+            let mut manifest = ModelManifest::from_config(fallback);
+            // old weights were trained on the original pipeline, not on today's config
+            manifest.preprocess = PreprocessConfig::default();
+            manifest
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut vs = nn::VarStore::new(device);
+    let model = build_model(&vs.root(), &manifest.model);
+    vs.load(weights)
+        .map_err(|e| format!("{}: {e}", weights.display()))?;
+
+    Ok((vs, model, manifest))
+}
+
+// Shape the flat [n, image_dim] pixels have to be viewed as before they go into the model
+pub fn input_shape(spec: &ModelSpec, n: i64) -> Vec<i64> {
+    match spec.architecture {
+        ModelArchitecture::Mlp => vec![n, spec.image_dim],
+        ModelArchitecture::Cnn => {
+            let side = image_side(spec);
+            vec![n, 1, side, side]
+        }
+    }
+}
+
+pub fn image_side(spec: &ModelSpec) -> i64 {
+    (spec.image_dim as f64).sqrt().round() as i64
+}
+
+pub fn build_model_mlp(vs: &nn::Path, spec: &ModelSpec) -> nn::Sequential {
     nn::seq()
-        .add(nn::linear(vs / "l1", cfg.image_dim, cfg.hidden, Default::default()))
+        .add(nn::linear(vs / "l1", spec.image_dim, spec.hidden, Default::default()))
         .add_fn(|x| x.relu())
-        .add(nn::linear(vs / "l2", cfg.hidden, cfg.labels, Default::default()))
+        .add(nn::linear(vs / "l2", spec.hidden, spec.labels, Default::default()))
 }
 
-pub fn build_model_cnn(vs: &nn::Path) -> nn::Sequential {
+pub fn build_model_cnn(vs: &nn::Path, spec: &ModelSpec) -> nn::Sequential {
     let c1 = nn::ConvConfig { padding: 1, ..Default::default() };
     let c2 = nn::ConvConfig { padding: 1, ..Default::default() };
 
+    // two 2x2 pools: 28 -> 14 -> 7
+    let pooled = image_side(spec) / 4;
+    let flat = 32 * pooled * pooled;
+
     nn::seq()
         .add(nn::conv2d(vs / "c1", 1, 16, 3, c1))
         .add_fn(|x| x.relu())
@@ -20,8 +79,8 @@ pub fn build_model_cnn(vs: &nn::Path) -> nn::Sequential {
         .add(nn::conv2d(vs / "c2", 16, 32, 3, c2))
         .add_fn(|x| x.relu())
         .add_fn(|x| x.max_pool2d_default(2))
-        .add_fn(|x| x.view([-1, 32 * 7 * 7]))
-        .add(nn::linear(vs / "fc1", 32 * 7 * 7, 128, Default::default()))
+        .add_fn(move |x| x.view([-1, flat]))
+        .add(nn::linear(vs / "fc1", flat, spec.hidden, Default::default()))
         .add_fn(|x| x.relu())
-        .add(nn::linear(vs / "fc2", 128, 10, Default::default()))
+        .add(nn::linear(vs / "fc2", spec.hidden, spec.labels, Default::default()))
 }
\ No newline at end of file
@@ -0,0 +1,371 @@
+﻿use crate::shared_lib::c_preprocess_config::{Brush, Centering, PreprocessConfig, Resize};
+use crate::shared_lib::c_sample::{CanvasRect, Stroke};
+
+fn splat_disk(buf: &mut [f32], w: i32, h: i32, cx: f32, cy: f32, r: f32) {
+    let min_x = (cx - r).floor() as i32;
+    let max_x = (cx + r).ceil() as i32;
+    let min_y = (cy - r).floor() as i32;
+    let max_y = (cy + r).ceil() as i32;
+
+    let r2 = r * r;
+
+    for y in min_y..=max_y {
+        if y < 0 || y >= h { continue; }
+        for x in min_x..=max_x {
+            if x < 0 || x >= w { continue; }
+            let dx = (x as f32 + 0.5) - cx;
+            let dy = (y as f32 + 0.5) - cy;
+            if dx*dx + dy*dy <= r2 {
+                let idx = (y as usize) * (w as usize) + (x as usize);
+                buf[idx] = 1.0;
+            }
+        }
+    }
+}
+// Segment a-b with the radius going from ra to rb, soft edge of `feather` pixels around the radius.
+// Overlaps keep the darker value, so crossings don't saturate.
+fn splat_capsule_soft(buf: &mut [f32], w: i32, h: i32, a: (f32, f32), b: (f32, f32), ra: f32, rb: f32) {
+    let reach = ra.max(rb) + 1.0;
+    let min_x = (a.0.min(b.0) - reach).floor().max(0.0) as i32;
+    let max_x = (a.0.max(b.0) + reach).ceil().min((w - 1) as f32) as i32;
+    let min_y = (a.1.min(b.1) - reach).floor().max(0.0) as i32;
+    let max_y = (a.1.max(b.1) + reach).ceil().min((h - 1) as f32) as i32;
+
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len2 = dx * dx + dy * dy;
+
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+            let t = if len2 > 0.0 { (((px - a.0) * dx + (py - a.1) * dy) / len2).clamp(0.0, 1.0) } else { 0.0 };
+            let (qx, qy) = (a.0 + dx * t - px, a.1 + dy * t - py);
+            let d = (qx * qx + qy * qy).sqrt();
+            let r = ra + (rb - ra) * t;
+
+            let feather = (0.35 * r).max(1.0);
+            let c = ((r - d) / feather + 0.5).clamp(0.0, 1.0);
+            let c = c * c * (3.0 - 2.0 * c);
+
+            let idx = (y as usize) * (w as usize) + (x as usize);
+            buf[idx] = buf[idx].max(c);
+        }
+    }
+}
+
+// side of the model input the pipeline produces
+const OUT_SIDE: i32 = 28;
+
+// hi_size x hi_size buffer, 0..1
+pub fn rasterize_strokes_to_hi(rect: &CanvasRect, strokes: &[Stroke], config: &PreprocessConfig) -> Vec<f32> {
+    let mut buf = vec![0.0f32; (config.hi_size * config.hi_size) as usize];
+    for s in strokes {
+        draw_one(&s.points, s.size, rect, config, &mut buf);
+    }
+
+    buf
+}
+
+fn to_hi(p: [f32; 2], rect: &CanvasRect, side: i32) -> (f32, f32) {
+    let lx = (p[0] - rect.min[0]) / rect.width();  // 0..1
+    let ly = (p[1] - rect.min[1]) / rect.height(); // 0..1
+    (lx * side as f32, ly * side as f32)
+}
+
+// Radius at every point; with `velocity` it shrinks where consecutive points are far apart
+// (the painter records one point per frame, so spacing is speed)
+fn point_radii(points: &[[f32; 2]], radius: f32, velocity: bool) -> Vec<f32> {
+    if !velocity || points.len() < 2 {
+        return vec![radius; points.len()];
+    }
+
+    let seg: Vec<f32> = points
+        .windows(2)
+        .map(|p| ((p[1][0] - p[0][0]).powi(2) + (p[1][1] - p[0][1]).powi(2)).sqrt())
+        .collect();
+
+    let mut radii = Vec::with_capacity(points.len());
+    let mut factor = 1.0;
+    for i in 0..points.len() {
+        let around: Vec<f32> = [i.checked_sub(1).map(|k| seg[k]), seg.get(i).copied()].into_iter().flatten().collect();
+        let speed = around.iter().sum::<f32>() / around.len() as f32;
+
+        // speed in brush radii per frame: ~0.2 slow -> 1.1x, ~3 fast -> 0.55x; smoothed along the stroke
+        let target = (1.15 - 0.2 * speed / radius.max(1.0)).clamp(0.55, 1.15);
+        factor = if i == 0 { target } else { 0.6 * factor + 0.4 * target };
+        radii.push(radius * factor);
+    }
+    radii
+}
+
+pub fn draw_one(points: &[[f32; 2]], radius_canvas: f32, rect: &CanvasRect, config: &PreprocessConfig, buf: &mut [f32]) {
+    if points.is_empty() { return; }
+
+    let side = config.hi_size;
+    let scale = side as f32 / rect.width();
+    let radii = point_radii(points, radius_canvas, config.velocity_width);
+
+    for (k, pair) in points.windows(2).enumerate() {
+        let (ax, ay) = to_hi(pair[0], rect, side);
+        let (bx, by) = to_hi(pair[1], rect, side);
+        let (ra, rb) = (radii[k] * scale, radii[k + 1] * scale);
+
+        if config.brush == Brush::AntiAliased {
+            splat_capsule_soft(buf, side, side, (ax, ay), (bx, by), ra, rb);
+            continue;
+        }
+        let r_hi = ra.min(rb);
+
+        let dx = bx - ax;
+        let dy = by - ay;
+        let dist = (dx * dx + dy * dy).sqrt();
+
+        let step = (r_hi * 0.5).max(1.0);
+        let steps = (dist / step).ceil() as i32;
+
+        for i in 0..=steps {
+            let t = if steps == 0 { 0.0 } else { i as f32 / steps as f32 };
+            let x = ax + dx * t;
+            let y = ay + dy * t;
+            splat_disk(buf, side, side, x, y, ra + (rb - ra) * t);
+        }
+    }
+}
+
+fn bbox_of_hi(hi: &[f32], side: i32, thr: f32) -> Option<(i32,i32,i32,i32)> {
+    let mut min_x = side;
+    let mut min_y = side;
+    let mut max_x = -1;
+    let mut max_y = -1;
+
+    for y in 0..side {
+        for x in 0..side {
+            let v = hi[(y as usize)* (side as usize) + (x as usize)];
+            if v > thr {
+                min_x = min_x.min(x);
+                min_y = min_y.min(y);
+                max_x = max_x.max(x);
+                max_y = max_y.max(y);
+            }
+        }
+    }
+    if max_x >= 0 { Some((min_x, min_y, max_x, max_y)) } else { None }
+}
+
+fn resize_bilinear(src: &[f32], sw: i32, sh: i32, dw: i32, dh: i32) -> Vec<f32> {
+    let mut out = vec![0.0f32; (dw*dh) as usize];
+    // a 1-pixel target samples the middle of the source
+    let step = |s: i32, d: i32| if d > 1 { (s as f32 - 1.0) / (d as f32 - 1.0) } else { 0.0 };
+    let (step_x, step_y) = (step(sw, dw), step(sh, dh));
+    let (off_x, off_y) = (if dw > 1 { 0.0 } else { (sw - 1) as f32 / 2.0 }, if dh > 1 { 0.0 } else { (sh - 1) as f32 / 2.0 });
+
+    for y in 0..dh {
+        let fy = off_y + (y as f32) * step_y;
+        let y0 = fy.floor() as i32;
+        let y1 = (y0 + 1).min(sh - 1);
+        let ty = fy - y0 as f32;
+
+        for x in 0..dw {
+            let fx = off_x + (x as f32) * step_x;
+            let x0 = fx.floor() as i32;
+            let x1 = (x0 + 1).min(sw - 1);
+            let tx = fx - x0 as f32;
+
+            let a = src[(y0 as usize)* (sw as usize) + (x0 as usize)];
+            let b = src[(y0 as usize)* (sw as usize) + (x1 as usize)];
+            let c = src[(y1 as usize)* (sw as usize) + (x0 as usize)];
+            let d = src[(y1 as usize)* (sw as usize) + (x1 as usize)];
+
+            let ab = a + (b - a) * tx;
+            let cd = c + (d - c) * tx;
+            out[(y as usize)* (dw as usize) + (x as usize)] = ab + (cd - ab) * ty;
+        }
+    }
+    out
+}
+
+// (x0, y0) .. (x0 + w, y0 + h) of the side x side buffer, pixels outside it read as 0
+fn crop_hi(hi: &[f32], side: i32, x0: i32, y0: i32, w: i32, h: i32) -> Vec<f32> {
+    let mut crop = vec![0.0f32; (w * h) as usize];
+    for y in 0..h {
+        for x in 0..w {
+            let (hx, hy) = (x0 + x, y0 + y);
+            if (0..side).contains(&hx) && (0..side).contains(&hy) {
+                crop[(y as usize) * (w as usize) + (x as usize)] = hi[(hy as usize) * (side as usize) + (hx as usize)];
+            }
+        }
+    }
+    crop
+}
+
+// For every target pixel along one axis: (source pixel, share of it) pairs, shares sum to 1
+fn area_weights(src: i32, dst: i32) -> Vec<Vec<(usize, f32)>> {
+    let ratio = src as f32 / dst as f32;
+    (0..dst)
+        .map(|i| {
+            let (lo, hi) = (i as f32 * ratio, (i + 1) as f32 * ratio);
+            (lo.floor() as i32..(hi.ceil() as i32).min(src))
+                .map(|j| (j as usize, ((j + 1) as f32).min(hi) - (j as f32).max(lo)))
+                .filter(|&(_, w)| w > 0.0)
+                .map(|(j, w)| (j, w / ratio))
+                .collect()
+        })
+        .collect()
+}
+
+// Box-filter downscale: each target pixel is the average of the source area it covers,
+// so thin strokes turn gray instead of disappearing between bilinear samples
+fn resize_area(src: &[f32], sw: i32, sh: i32, dw: i32, dh: i32) -> Vec<f32> {
+    let (wx, wy) = (area_weights(sw, dw), area_weights(sh, dh));
+
+    let mut rows = vec![0.0f32; (dw * sh) as usize];
+    for y in 0..sh as usize {
+        for (x, weights) in wx.iter().enumerate() {
+            rows[y * dw as usize + x] = weights.iter().map(|&(j, w)| src[y * sw as usize + j] * w).sum();
+        }
+    }
+
+    let mut out = vec![0.0f32; (dw * dh) as usize];
+    for (y, weights) in wy.iter().enumerate() {
+        for x in 0..dw as usize {
+            out[y * dw as usize + x] = weights.iter().map(|&(j, w)| rows[j * dw as usize + x] * w).sum();
+        }
+    }
+    out
+}
+
+fn resize(src: &[f32], sw: i32, sh: i32, dw: i32, dh: i32, kind: Resize) -> Vec<f32> {
+    match kind {
+        Resize::Bilinear => resize_bilinear(src, sw, sh, dw, dh),
+        Resize::Area => resize_area(src, sw, sh, dw, dh),
+    }
+}
+
+pub fn hi_to_mnist28(hi: &[f32], config: &PreprocessConfig) -> Vec<f32> {
+    let mut out28 = vec![0.0f32; (OUT_SIDE * OUT_SIDE) as usize];
+    let hi_side = config.hi_size;
+    let target = config.target_size.clamp(1, OUT_SIDE);
+
+    let Some((min_x, min_y, max_x, max_y)) = bbox_of_hi(hi, hi_side, config.bbox_threshold) else {
+        return out28;
+    };
+    if config.centering == Centering::CenterOfMass {
+        return center_of_mass_28(hi, (min_x, min_y, max_x, max_y), config);
+    }
+
+    // делаем квадратный bbox + паддинг
+    let bw = max_x - min_x + 1;
+    let bh = max_y - min_y + 1;
+    let side = bw.max(bh);
+
+    let pad = (side as f32 * config.padding).ceil() as i32; // 20% поля (важно для 6/9)
+    let cx = (min_x + max_x) / 2;
+    let cy = (min_y + max_y) / 2;
+
+    // clamped to the canvas like the original pipeline, so a digit near the edge gets squeezed
+    let half = side / 2 + pad;
+    let x0 = (cx - half).clamp(0, hi_side - 1);
+    let y0 = (cy - half).clamp(0, hi_side - 1);
+    let x1 = (cx + half).clamp(0, hi_side - 1);
+    let y1 = (cy + half).clamp(0, hi_side - 1);
+
+    let cw = x1 - x0 + 1;
+    let ch = y1 - y0 + 1;
+    let crop = crop_hi(hi, hi_side, x0, y0, cw, ch);
+
+    // resize crop -> target_size x target_size
+    let small = resize(&crop, cw, ch, target, target, config.resize);
+
+    // вставляем в центр 28x28
+    let offset = (OUT_SIDE - target) / 2;
+    for y in 0..target {
+        for x in 0..target {
+            out28[((y + offset) * OUT_SIDE + (x + offset)) as usize] = small[(y * target + x) as usize].clamp(0.0, 1.0);
+        }
+    }
+
+    out28
+}
+
+// MNIST's own normalization: the bounding box scaled (area-averaged) so its longer side is target_size pixels (aspect ratio kept),
+// then placed in 28x28 with the intensity center of mass on the center
+fn center_of_mass_28(hi: &[f32], (min_x, min_y, max_x, max_y): (i32, i32, i32, i32), config: &PreprocessConfig) -> Vec<f32> {
+    let bw = max_x - min_x + 1;
+    let bh = max_y - min_y + 1;
+    let crop = crop_hi(hi, config.hi_size, min_x, min_y, bw, bh);
+
+    let target = config.target_size.clamp(1, OUT_SIDE);
+    let scale = target as f32 / bw.max(bh) as f32;
+    let dw = ((bw as f32 * scale).round() as i32).clamp(1, target);
+    let dh = ((bh as f32 * scale).round() as i32).clamp(1, target);
+    // always area: a tall digit shrinks ~15x here and bilinear samples would skip thin strokes
+    let small = resize_area(&crop, bw, bh, dw, dh);
+
+    let (mut mass, mut mx, mut my) = (0.0f32, 0.0f32, 0.0f32);
+    for y in 0..dh {
+        for x in 0..dw {
+            let v = small[(y * dw + x) as usize];
+            mass += v;
+            mx += v * (x as f32 + 0.5);
+            my += v * (y as f32 + 0.5);
+        }
+    }
+    let (com_x, com_y) = if mass > 0.0 { (mx / mass, my / mass) } else { (dw as f32 / 2.0, dh as f32 / 2.0) };
+    let middle = OUT_SIDE as f32 / 2.0;
+    let off_x = (middle - com_x).round() as i32;
+    let off_y = (middle - com_y).round() as i32;
+
+    let mut out28 = vec![0.0f32; (OUT_SIDE * OUT_SIDE) as usize];
+    for y in 0..dh {
+        for x in 0..dw {
+            let (tx, ty) = (x + off_x, y + off_y);
+            if (0..OUT_SIDE).contains(&tx) && (0..OUT_SIDE).contains(&ty) {
+                out28[(ty * OUT_SIDE + tx) as usize] = small[(y * dw + x) as usize].clamp(0.0, 1.0);
+            }
+        }
+    }
+    out28
+}
+
+// The whole preprocessing pipeline: strokes on the canvas -> 28x28 in 0..1
+pub fn rasterize_to_mnist28(rect: &CanvasRect, strokes: &[Stroke], config: &PreprocessConfig) -> Vec<f32> {
+    hi_to_mnist28(&rasterize_strokes_to_hi(rect, strokes, config), config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Output of the original pipeline for a stroke close to the canvas edge, where the crop is clamped
+    #[test]
+    fn default_matches_original_pipeline() {
+        let rect = CanvasRect{ min: [0.0, 0.0], max: [280.0, 280.0] };
+        let strokes = vec![Stroke{ size: 10.0, points: vec![[12.0, 20.0], [40.0, 150.0], [90.0, 60.0]] }];
+        let out = rasterize_to_mnist28(&rect, &strokes, &PreprocessConfig::default());
+
+        assert_eq!(out.len(), 28 * 28);
+        assert!((out.iter().sum::<f32>() - 59.3352).abs() < 1e-3);
+        assert_eq!(out.iter().filter(|&&v| v > 0.5).count(), 58);
+        for (i, expected) in [(0, 0.0), (145, 0.149585), (173, 1.0), (267, 0.736843), (316, 0.368420), (351, 0.263153)] {
+            assert!((out[i] - expected).abs() < 1e-4, "pixel {i}: {} instead of {expected}", out[i]);
+        }
+    }
+
+    // a tall thin "1" shrinks ~15x; the configured bilinear resize must not make it vanish
+    #[test]
+    fn center_of_mass_keeps_thin_strokes() {
+        let rect = CanvasRect{ min: [0.0, 0.0], max: [420.0, 420.0] };
+        let strokes = vec![Stroke{ size: 20.0, points: vec![[210.0, 20.0], [210.0, 400.0]] }];
+        let config = PreprocessConfig{
+            brush: Brush::AntiAliased,
+            centering: Centering::CenterOfMass,
+            resize: Resize::Bilinear,
+            ..Default::default()
+        };
+        let out = rasterize_to_mnist28(&rect, &strokes, &config);
+
+        let ink: f32 = out.iter().sum();
+        let max = out.iter().cloned().fold(0.0f32, f32::max);
+        assert!(ink > 10.0, "only {ink} ink left");
+        assert!(max > 0.5, "brightest pixel {max}");
+    }
+}
\ No newline at end of file
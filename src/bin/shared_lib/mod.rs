@@ -1,2 +1,10 @@
 pub mod c_trainer_config;
-pub mod f_ai_data;
\ No newline at end of file
+pub mod f_ai_data;
+pub mod c_model_manifest;
+pub mod c_optimizer_settings;
+pub mod c_finetune_config;
+pub mod c_augment_config;
+pub mod c_epoch_metrics;
+pub mod c_sample;
+pub mod f_rasterize;
+pub mod c_preprocess_config;
\ No newline at end of file
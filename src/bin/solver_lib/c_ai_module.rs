@@ -1,12 +1,15 @@
 ﻿use eframe::egui;
 use tch::{nn, Device};
+use crate::shared_lib::c_model_manifest::ModelManifest;
 use crate::shared_lib::c_trainer_config::TrainerConfig;
-use crate::shared_lib::f_ai_data::build_model;
+use crate::shared_lib::f_ai_data::load_checkpoint;
 
 pub struct AIModule {
     pub device: Device,
     pub vs: nn::VarStore,
     pub model: nn::Sequential,
+    pub manifest: ModelManifest,
+    pub model_path: String,
     pub probs: [f32; 10],
     pub predicted: Option<i64>,
     pub last_28_pixels: Option<Vec<f32>>
@@ -16,19 +19,27 @@ pub struct AIModule {
 impl AIModule{
     pub fn new(config: &TrainerConfig) -> Self {
         let device = Device::cuda_if_available();
-        let mut vs = nn::VarStore::new(device);
-        let root = &vs.root();
-        let model = build_model(root, &config);
-        vs.load(config.out_path.clone()).expect("Failed to load models/mnist.ot");
+        let path = config.inference_model_path();
+        let (vs, model, manifest) = load_checkpoint(path, config, device)
+            .unwrap_or_else(|e| panic!("Failed to load {path}: {e}"));
 
 
         Self{
             device,
             vs,
             model,
+            manifest,
+            model_path: path.to_string(),
             probs: [0.0; 10],
             predicted: None,
             last_28_pixels: None,
         }
     }
+
+    // forget the last prediction, e.g. when the canvas is cleared
+    pub fn clear(&mut self) {
+        self.probs = [0.0; 10];
+        self.predicted = None;
+        self.last_28_pixels = None;
+    }
 }
\ No newline at end of file
@@ -0,0 +1,239 @@
+﻿use std::collections::HashMap;
+use std::path::PathBuf;
+use eframe::egui;
+use eframe::egui::{Color32, StrokeKind};
+use crate::shared_lib::c_sample::{load_samples, Sample, SAMPLE_SIDE};
+use crate::shared_lib::c_trainer_config::TrainerConfig;
+use crate::solver_lib::c_ai_module::AIModule;
+use crate::solver_lib::f_utils::predict_batch;
+
+const THUMB_SIZE: f32 = 36.0;
+
+// Window for curating mydata/: thumbnails per label, relabel / delete / move, disagreement filter
+pub struct DatasetView {
+    pub open: bool,
+    dir: PathBuf,
+    // reload from disk on the next frame the window is shown
+    stale: bool,
+
+    samples: Vec<Sample>,
+    // (class, probability) of the loaded model, per sample
+    predictions: Vec<(i64, f32)>,
+    textures: HashMap<PathBuf, egui::TextureHandle>,
+
+    selected: Option<usize>,
+    only_disagreeing: bool,
+    relabel_to: u8,
+    move_dir: String,
+    status: Option<String>,
+}
+
+impl DatasetView {
+    pub fn new(config: &TrainerConfig) -> Self {
+        Self{
+            open: false,
+            dir: PathBuf::from(&config.finetune.data_dir),
+            stale: true,
+            samples: vec![],
+            predictions: vec![],
+            textures: HashMap::new(),
+            selected: None,
+            only_disagreeing: false,
+            relabel_to: 0,
+            move_dir: "mydata_moved/".to_string(),
+            status: None,
+        }
+    }
+
+    // Something changed on disk, e.g. a new sample was saved
+    pub fn mark_stale(&mut self) {
+        self.stale = true;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, ai_module: &AIModule) {
+        if !self.open {
+            return;
+        }
+        if self.stale {
+            self.reload(ai_module);
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Dataset")
+            .open(&mut open)
+            .default_width(460.0)
+            .show(ctx, |ui| self.draw(ui));
+        self.open = open;
+    }
+
+    fn reload(&mut self, ai_module: &AIModule) {
+        let (samples, mut problems) = load_samples(&self.dir);
+
+        // other sizes can't go through the model, they show up as disagreeing
+        let image_dim = ai_module.manifest.model.image_dim;
+        for s in samples.iter().filter(|s| s.pixels.len() as i64 != image_dim) {
+            problems.push(format!("{}: {} pixels, the model takes {image_dim}", s.path.display(), s.pixels.len()));
+        }
+        self.status = (!problems.is_empty()).then(|| format!("{} problems: {}", problems.len(), problems.join("; ")));
+
+        let images: Vec<Vec<f32>> = samples.iter().map(|s| s.pixels01()).collect();
+        self.predictions = predict_batch(ai_module, &images);
+        self.samples = samples;
+        self.textures.clear();
+        self.selected = None;
+        self.stale = false;
+    }
+
+    fn disagrees(&self, i: usize) -> bool {
+        self.predictions.get(i).is_some_and(|&(class, _)| class != self.samples[i].label as i64)
+    }
+
+    fn draw(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.button("Refresh").clicked() {
+                self.stale = true;
+            }
+            ui.checkbox(&mut self.only_disagreeing, "Only where the model disagrees");
+        });
+
+        let mut counts = [0usize; 10];
+        for s in &self.samples {
+            counts[s.label as usize % 10] += 1;
+        }
+        let disagreeing = (0..self.samples.len()).filter(|&i| self.disagrees(i)).count();
+        ui.label(format!("{} samples in {}, model disagrees on {disagreeing}", self.samples.len(), self.dir.display()));
+        ui.horizontal_wrapped(|ui| {
+            for (label, n) in counts.iter().enumerate() {
+                ui.monospace(format!("{label}: {n:<4}"));
+            }
+        });
+        if let Some(status) = &self.status {
+            ui.colored_label(Color32::YELLOW, status);
+        }
+        ui.separator();
+
+        let ctx = ui.ctx().clone();
+        egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+            for label in 0..10u8 {
+                let indices: Vec<usize> = (0..self.samples.len())
+                    .filter(|&i| self.samples[i].label == label && (!self.only_disagreeing || self.disagrees(i)))
+                    .collect();
+                if indices.is_empty() {
+                    continue;
+                }
+
+                ui.label(format!("{label} ({})", indices.len()));
+                ui.horizontal_wrapped(|ui| {
+                    for i in indices {
+                        let sample = &self.samples[i];
+                        let texture = self.textures.entry(sample.path.clone()).or_insert_with(|| {
+                            let (w, h) = sample.meta.as_ref().map(|m| (m.width, m.height)).unwrap_or((SAMPLE_SIDE, SAMPLE_SIDE));
+                            let image = egui::ColorImage::from_gray([w as usize, h as usize], &sample.pixels);
+                            ctx.load_texture(sample.path.display().to_string(), image, egui::TextureOptions::NEAREST)
+                        });
+
+                        let response = ui.add(
+                            egui::Image::new(&*texture)
+                                .fit_to_exact_size(egui::vec2(THUMB_SIZE, THUMB_SIZE))
+                                .sense(egui::Sense::click()),
+                        );
+
+                        let frame = if self.selected == Some(i) {
+                            Some(Color32::LIGHT_BLUE)
+                        } else if self.disagrees(i) {
+                            Some(Color32::RED)
+                        } else {
+                            None
+                        };
+                        if let Some(color) = frame {
+                            ui.painter().rect_stroke(response.rect, 0.0, egui::Stroke::new(2.0, color), StrokeKind::Outside);
+                        }
+
+                        let (class, prob) = self.predictions.get(i).copied().unwrap_or((-1, 0.0));
+                        if response.on_hover_text(format!("model: {class} ({:.0}%)", 100.0 * prob)).clicked() {
+                            self.selected = Some(i);
+                            self.relabel_to = label;
+                        }
+                    }
+                });
+            }
+        });
+
+        ui.separator();
+        self.draw_selected(ui);
+    }
+
+    fn draw_selected(&mut self, ui: &mut egui::Ui) {
+        let Some(i) = self.selected.filter(|&i| i < self.samples.len()) else {
+            ui.label("Click a sample to edit it.");
+            return;
+        };
+
+        let sample = &self.samples[i];
+        ui.label(sample.path.display().to_string());
+        let (class, prob) = self.predictions.get(i).copied().unwrap_or((-1, 0.0));
+        ui.label(format!("label {} | model says {class} ({:.1}%)", sample.label, 100.0 * prob));
+        if let Some(meta) = &sample.meta {
+            let author = if meta.author.is_empty() { "-" } else { meta.author.as_str() };
+            ui.label(format!("author {author} | saved with prediction {:?} | {} strokes", meta.prediction, meta.strokes.len()));
+        } else {
+            ui.label("legacy sample, no metadata");
+        }
+
+        let mut edit = None;
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("relabel_to")
+                .selected_text(self.relabel_to.to_string())
+                .show_ui(ui, |ui| {
+                    for d in 0u8..=9 {
+                        ui.selectable_value(&mut self.relabel_to, d, d.to_string());
+                    }
+                });
+            if ui.button("Relabel").clicked() {
+                edit = Some(Edit::Relabel(self.relabel_to));
+            }
+            if ui.button("Delete").clicked() {
+                edit = Some(Edit::Delete);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.move_dir);
+            if ui.button("Move there").clicked() {
+                edit = Some(Edit::Move(PathBuf::from(&self.move_dir)));
+            }
+        });
+
+        if let Some(edit) = edit {
+            self.apply(i, edit);
+        }
+    }
+
+    fn apply(&mut self, i: usize, edit: Edit) {
+        let old_path = self.samples[i].path.clone();
+        let from = self.samples[i].label;
+        let result = match &edit {
+            Edit::Relabel(label) if *label == from => Ok(format!("already labeled {label}")),
+            Edit::Relabel(label) => self.samples[i].move_to(&self.dir, *label).map(|_| format!("relabeled {from} -> {label}")),
+            Edit::Move(root) => self.samples[i].move_to(root, from).map(|_| format!("moved to {}", self.samples[i].path.display())),
+            Edit::Delete => self.samples[i].delete().map(|_| format!("deleted {}", old_path.display())),
+        };
+        self.status = Some(result.unwrap_or_else(|e| format!("can't update {}: {e}", old_path.display())));
+        self.textures.remove(&old_path);
+
+        // gone from the data folder (deleted or moved elsewhere)
+        let path = &self.samples[i].path;
+        if (matches!(edit, Edit::Delete) && !path.exists()) || !path.starts_with(&self.dir) {
+            self.samples.remove(i);
+            if i < self.predictions.len() {
+                self.predictions.remove(i);
+            }
+            self.selected = None;
+        }
+    }
+}
+
+enum Edit {
+    Relabel(u8),
+    Move(PathBuf),
+    Delete,
+}
\ No newline at end of file
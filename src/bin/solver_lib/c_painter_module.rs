@@ -1,5 +1,7 @@
 ﻿use eframe::egui;
 use eframe::egui::{Context, StrokeKind, Ui};
+use crate::shared_lib::c_preprocess_config::PreprocessConfig;
+use crate::shared_lib::c_sample::{CanvasRect, Stroke};
 
 pub struct PainterModule {
     pub canvas_rect: Option<egui::Rect>,
@@ -7,6 +9,8 @@ pub struct PainterModule {
     pub sizes: Vec<f32>,
     pub current_stroke: Vec<egui::Pos2>,
     pub brush_size: f32,
+    // what the model sees, the canvas itself always shows hard dots
+    pub preprocess: PreprocessConfig,
 }
 
 impl PainterModule {
@@ -83,6 +87,32 @@ impl PainterModule {
         return check;
     }
 
+    pub fn canvas(&self) -> Option<CanvasRect> {
+        self.canvas_rect.map(|r| CanvasRect {
+            min: [r.min.x, r.min.y],
+            max: [r.max.x, r.max.y],
+        })
+    }
+
+    // Finished strokes plus the one being drawn, in the shared sample format
+    pub fn strokes_with_current(&self) -> Vec<Stroke> {
+        let to_stroke = |points: &[egui::Pos2], size: f32| Stroke {
+            size,
+            points: points.iter().map(|p| [p.x, p.y]).collect(),
+        };
+
+        let mut strokes: Vec<Stroke> = self
+            .strokes
+            .iter()
+            .zip(&self.sizes)
+            .map(|(points, &size)| to_stroke(points, size))
+            .collect();
+        if !self.current_stroke.is_empty() {
+            strokes.push(to_stroke(&self.current_stroke, self.brush_size));
+        }
+        strokes
+    }
+
     pub fn clear(&mut self) {
         self.strokes.clear();
         self.current_stroke.clear();
@@ -100,6 +130,7 @@ impl Default for PainterModule {
             sizes: vec![],
             current_stroke: vec![],
             brush_size: 20.0,
+            preprocess: PreprocessConfig::default(),
         }
     }
 }
@@ -1,27 +1,43 @@
 ﻿use std::env;
+use crate::shared_lib::c_preprocess_config::{Brush, Centering, Resize};
 use crate::shared_lib::c_trainer_config::TrainerConfig;
 use crate::solver_lib::c_ai_module::AIModule;
 use crate::solver_lib::c_painter_module::PainterModule;
+use crate::solver_lib::c_training_view::TrainingView;
+use crate::solver_lib::c_dataset_view::DatasetView;
 use eframe::egui;
-use crate::solver_lib::f_utils::{predict_from_canvas, save_bmp_gray_f32, save_sample_u8};
+use crate::solver_lib::f_utils::{predict_from_canvas, save_bmp_gray_f32, save_sample};
 
 pub struct SolverApp {
     config: TrainerConfig,
     painter_data: PainterModule,
     ai_module: AIModule,
+    training_view: TrainingView,
+    dataset_view: DatasetView,
 
     selected_label: u8,
+    // stored with every saved sample
+    author: String,
 }
 
 impl SolverApp {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         let config = TrainerConfig::new();
+        let ai_module = AIModule::new(&config);
+        // feed the model what it was trained on
+        let painter_data = PainterModule {
+            preprocess: ai_module.manifest.preprocess,
+            ..PainterModule::default()
+        };
 
         Self{
-            painter_data: PainterModule::default(),
-            ai_module: AIModule::new(&config),
+            painter_data,
+            training_view: TrainingView::new(&config, &ai_module.manifest),
+            dataset_view: DatasetView::new(&config),
+            ai_module,
             config,
             selected_label: 0,
+            author: env::var("USER").or_else(|_| env::var("USERNAME")).unwrap_or_default(),
         }
     }
 }
@@ -36,6 +52,7 @@ impl eframe::App for SolverApp {
 
         if clear_pressed {
             self.painter_data.clear();
+            self.ai_module.clear();
         }
 
 
@@ -43,7 +60,7 @@ impl eframe::App for SolverApp {
             ui.heading("Draw a digit");
 
             if (self.painter_data.draw_painter_panel(ui)){
-                predict_from_canvas(&self.painter_data, &mut self.ai_module, &self.config);
+                predict_from_canvas(&self.painter_data, &mut self.ai_module);
             }
 
             ui.add_space(10.0);
@@ -57,8 +74,58 @@ impl eframe::App for SolverApp {
             .min_width(160.0).show(ctx, |ui| {
 
             ui.add(egui::Slider::new(&mut self.painter_data.brush_size, 3.0..=20.0).text("Brush size"));
+
+            let preprocess_before = self.painter_data.preprocess;
+            egui::ComboBox::from_label("Rasterizer")
+                .selected_text(match self.painter_data.preprocess.brush {
+                    Brush::Hard => "Hard",
+                    Brush::AntiAliased => "Anti-aliased",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.painter_data.preprocess.brush, Brush::Hard, "Hard");
+                    ui.selectable_value(&mut self.painter_data.preprocess.brush, Brush::AntiAliased, "Anti-aliased");
+                });
+            ui.checkbox(&mut self.painter_data.preprocess.velocity_width, "Thinner when fast");
+            egui::ComboBox::from_label("Centering")
+                .selected_text(match self.painter_data.preprocess.centering {
+                    Centering::BoundingBox => "Bounding box",
+                    Centering::CenterOfMass => "Center of mass",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.painter_data.preprocess.centering, Centering::BoundingBox, "Bounding box");
+                    ui.selectable_value(&mut self.painter_data.preprocess.centering, Centering::CenterOfMass, "Center of mass");
+                });
+            // center of mass always resizes by area
+            ui.add_enabled_ui(self.painter_data.preprocess.centering == Centering::BoundingBox, |ui| {
+                egui::ComboBox::from_label("Resize")
+                    .selected_text(match self.painter_data.preprocess.resize {
+                        Resize::Bilinear => "Bilinear",
+                        Resize::Area => "Area",
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.painter_data.preprocess.resize, Resize::Bilinear, "Bilinear");
+                        ui.selectable_value(&mut self.painter_data.preprocess.resize, Resize::Area, "Area");
+                    });
+            });
+            if self.painter_data.preprocess != self.ai_module.manifest.preprocess {
+                ui.colored_label(egui::Color32::YELLOW, "Not the preprocessing the model was trained with");
+                if ui.button("Use the model's").clicked() {
+                    self.painter_data.preprocess = self.ai_module.manifest.preprocess;
+                }
+            }
+            if self.painter_data.preprocess != preprocess_before && !self.painter_data.strokes.is_empty() {
+                predict_from_canvas(&self.painter_data, &mut self.ai_module);
+            }
+
             if (ui.button("Clear").clicked()){
                 self.painter_data.clear();
+                self.ai_module.clear();
+            }
+            if ui.button("Training curves").clicked() {
+                self.training_view.open = !self.training_view.open;
+            }
+            if ui.button("Dataset").clicked() {
+                self.dataset_view.open = !self.dataset_view.open;
             }
 
             // if ui.button("Save 28x28 BMP").clicked() {
@@ -83,11 +150,18 @@ impl eframe::App for SolverApp {
                     }
                 });
 
+            ui.horizontal(|ui| {
+                ui.label("Author");
+                ui.text_edit_singleline(&mut self.author);
+            });
+
             if (ui.button("Save correct answer").clicked()){
 
-                if let Some(v) = self.ai_module.last_28_pixels.as_ref() {
-                    if let Err(e) = save_sample_u8(v, self.selected_label) {
-                        eprintln!("Can't save correct answer: {e}");
+                if self.ai_module.last_28_pixels.is_some() && !self.painter_data.strokes_with_current().is_empty() {
+                    let dir = &self.config.finetune.data_dir;
+                    match save_sample(dir, &self.painter_data, &self.ai_module, self.selected_label, &self.author) {
+                        Ok(_) => self.dataset_view.mark_stale(),
+                        Err(e) => eprintln!("Can't save correct answer: {e}"),
                     }
                 }
 
@@ -114,6 +188,9 @@ impl eframe::App for SolverApp {
                 ui.label(egui::RichText::new(text).size(size).color(color));
             }
         });
+
+        self.training_view.show(ctx);
+        self.dataset_view.show(ctx, &self.ai_module);
     }
 }
 
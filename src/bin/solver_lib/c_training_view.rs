@@ -0,0 +1,222 @@
+﻿use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use eframe::egui;
+use eframe::egui::Color32;
+use serde::Deserialize;
+use crate::shared_lib::c_epoch_metrics::EpochMetrics;
+use crate::shared_lib::c_model_manifest::ModelManifest;
+use crate::shared_lib::c_trainer_config::TrainerConfig;
+use crate::solver_lib::f_plot::{confusion_heatmap, line_chart};
+
+// The parts of a trainer evaluation report (<model>_eval_<data>.json) the viewer shows
+#[derive(Deserialize)]
+struct EvalReportView {
+    model: String,
+    data: String,
+    accuracy: f64,
+    confusion: Vec<Vec<i64>>,
+    per_class: Vec<ClassView>,
+}
+
+#[derive(Deserialize)]
+struct ClassView {
+    label: String,
+}
+
+// Window with the loss / accuracy curves and confusion matrix of a trainer run folder
+pub struct TrainingView {
+    pub open: bool,
+    runs_dir: PathBuf,
+    runs: Vec<PathBuf>,
+    selected: Option<PathBuf>,
+
+    metrics: Vec<EpochMetrics>,
+    reports: Vec<PathBuf>,
+    selected_report: usize,
+    report: Option<EvalReportView>,
+    error: Option<String>,
+}
+
+impl TrainingView {
+    // Starts on the run that produced the loaded model, the newest run otherwise
+    pub fn new(config: &TrainerConfig, manifest: &ModelManifest) -> Self {
+        let mut view = Self{
+            open: false,
+            runs_dir: PathBuf::from(&config.runs_dir),
+            runs: vec![],
+            selected: None,
+            metrics: vec![],
+            reports: vec![],
+            selected_report: 0,
+            report: None,
+            error: None,
+        };
+        view.scan_runs();
+
+        let run = manifest
+            .run_dir
+            .as_ref()
+            .map(PathBuf::from)
+            .filter(|p| p.join("metrics.jsonl").exists())
+            .or_else(|| view.runs.first().cloned());
+        if let Some(run) = run {
+            view.select_run(run);
+        }
+        view
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let mut open = self.open;
+        egui::Window::new("Training")
+            .open(&mut open)
+            .default_width(440.0)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| self.draw(ui));
+            });
+        self.open = open;
+    }
+
+    // Run folders with a metrics log, newest first
+    fn scan_runs(&mut self) {
+        let mut runs: Vec<PathBuf> = fs::read_dir(&self.runs_dir)
+            .map(|rd| rd.flatten().map(|e| e.path()).filter(|p| p.join("metrics.jsonl").exists()).collect())
+            .unwrap_or_default();
+        runs.sort();
+        runs.reverse();
+        self.runs = runs;
+    }
+
+    fn select_run(&mut self, run: PathBuf) {
+        self.error = None;
+        self.metrics = EpochMetrics::load_jsonl(run.join("metrics.jsonl")).unwrap_or_else(|e| {
+            self.error = Some(e.to_string());
+            vec![]
+        });
+
+        let mut reports: Vec<PathBuf> = fs::read_dir(&run)
+            .map(|rd| {
+                rd.flatten()
+                    .map(|e| e.path())
+                    .filter(|p| {
+                        let name = p.file_name().and_then(|n| n.to_str()).unwrap_or("");
+                        name.contains("_eval_") && name.ends_with(".json")
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        reports.sort();
+        self.reports = reports;
+        // the fine-tuned model's report sorts last
+        self.select_report(self.reports.len().saturating_sub(1));
+
+        self.selected = Some(run);
+    }
+
+    fn select_report(&mut self, index: usize) {
+        self.selected_report = index;
+        self.report = None;
+
+        let Some(path) = self.reports.get(index) else { return; };
+        let parsed = fs::File::open(path)
+            .map_err(|e| e.to_string())
+            .and_then(|f| serde_json::from_reader(BufReader::new(f)).map_err(|e| e.to_string()));
+        match parsed {
+            Ok(report) => self.report = Some(report),
+            Err(e) => self.error = Some(format!("{}: {e}", path.display())),
+        }
+    }
+
+    fn draw(&mut self, ui: &mut egui::Ui) {
+        let name = |p: &Path| p.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+
+        let mut pick_run = None;
+        ui.horizontal(|ui| {
+            let current = self.selected.as_deref().map(name).unwrap_or_else(|| "-".to_string());
+            egui::ComboBox::from_label("Run")
+                .selected_text(current)
+                .show_ui(ui, |ui| {
+                    for run in &self.runs {
+                        let selected = self.selected.as_ref() == Some(run);
+                        if ui.selectable_label(selected, name(run)).clicked() {
+                            pick_run = Some(run.clone());
+                        }
+                    }
+                });
+            if ui.button("Refresh").clicked() {
+                self.scan_runs();
+                pick_run = self.selected.clone().or_else(|| self.runs.first().cloned());
+            }
+        });
+        if let Some(run) = pick_run {
+            self.select_run(run);
+        }
+
+        if let Some(e) = &self.error {
+            ui.colored_label(Color32::RED, e);
+        }
+        if self.selected.is_none() {
+            ui.label(format!("No runs in {} yet, train with trainer_app first.", self.runs_dir.display()));
+            return;
+        }
+
+        // final test rows of each stage
+        for m in self.metrics.iter().filter(|m| m.is_test()) {
+            if let Some(acc) = m.test_acc {
+                ui.label(format!("{}: MNIST test acc {:5.2}% (epoch {})", m.stage, 100.0 * acc, m.epoch));
+            }
+        }
+
+        let epochs: Vec<&EpochMetrics> = self.metrics.iter().filter(|m| !m.is_test()).collect();
+        let separators: Vec<usize> = (1..epochs.len()).filter(|&i| epochs[i].stage != epochs[i - 1].stage).collect();
+        let column = |stage: &str, value: fn(&EpochMetrics) -> Option<f64>| -> Vec<Option<f64>> {
+            epochs.iter().map(|m| if m.stage == stage { value(m) } else { None }).collect()
+        };
+
+        ui.separator();
+        ui.label("Loss per epoch");
+        line_chart(
+            ui,
+            &[
+                ("train", Color32::from_rgb(60, 120, 220), column("train", |m| m.loss)),
+                ("fine-tune", Color32::from_rgb(230, 140, 40), column("finetune", |m| m.loss)),
+            ],
+            &separators,
+        );
+
+        ui.separator();
+        ui.label("Accuracy per epoch");
+        line_chart(
+            ui,
+            &[
+                ("train", Color32::from_rgb(60, 120, 220), column("train", |m| m.train_acc)),
+                ("val", Color32::from_rgb(40, 170, 90), column("train", |m| m.val_acc)),
+                ("mydata", Color32::from_rgb(230, 140, 40), column("finetune", |m| m.train_acc)),
+                ("mydata held-out", Color32::from_rgb(170, 70, 200), column("finetune", |m| m.val_acc)),
+            ],
+            &separators,
+        );
+
+        ui.separator();
+        let mut pick_report = None;
+        let current = self.reports.get(self.selected_report).map(|p| name(p)).unwrap_or_else(|| "-".to_string());
+        egui::ComboBox::from_label("Report")
+            .selected_text(current)
+            .show_ui(ui, |ui| {
+                for (i, report) in self.reports.iter().enumerate() {
+                    if ui.selectable_label(i == self.selected_report, name(report)).clicked() {
+                        pick_report = Some(i);
+                    }
+                }
+            });
+        if let Some(i) = pick_report {
+            self.select_report(i);
+        }
+
+        if let Some(report) = &self.report {
+            ui.label(format!("{} on {}: acc {:5.2}%", report.model, report.data, 100.0 * report.accuracy));
+            let labels: Vec<String> = report.per_class.iter().map(|c| c.label.clone()).collect();
+            confusion_heatmap(ui, &labels, &report.confusion);
+        }
+    }
+}
\ No newline at end of file
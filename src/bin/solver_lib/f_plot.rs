@@ -0,0 +1,137 @@
+﻿use eframe::egui;
+use eframe::egui::{Align2, Color32, FontId, Pos2, Rect, Sense, Stroke, StrokeKind, Ui};
+
+const AXIS_COLOR: Color32 = Color32::GRAY;
+
+// Line chart, x is the index into each series, None leaves a gap.
+// `separators` draws a vertical line before those indices (e.g. train -> fine-tune).
+pub fn line_chart(ui: &mut Ui, series: &[(&str, Color32, Vec<Option<f64>>)], separators: &[usize]) {
+    let width = ui.available_width().max(200.0);
+    let (response, painter) = ui.allocate_painter(egui::vec2(width, 150.0), Sense::hover());
+    let plot = Rect::from_min_max(
+        response.rect.min + egui::vec2(44.0, 6.0),
+        response.rect.max - egui::vec2(6.0, 16.0),
+    );
+    painter.rect_stroke(plot, 0.0, Stroke::new(1.0, AXIS_COLOR), StrokeKind::Middle);
+
+    let font = FontId::proportional(10.0);
+    let n = series.iter().map(|s| s.2.len()).max().unwrap_or(0);
+    let (lo, hi) = series
+        .iter()
+        .flat_map(|s| s.2.iter().flatten())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+    if n == 0 || !lo.is_finite() {
+        painter.text(plot.center(), Align2::CENTER_CENTER, "no data", font, AXIS_COLOR);
+        return;
+    }
+    let (lo, hi) = if hi - lo < 1e-9 { (lo - 0.5, hi + 0.5) } else { (lo, hi) };
+
+    let to_x = |i: f64| plot.left() + plot.width() * if n > 1 { (i / (n - 1) as f64) as f32 } else { 0.5 };
+    let to_y = |v: f64| plot.bottom() - plot.height() * ((v - lo) / (hi - lo)) as f32;
+
+    painter.text(Pos2::new(plot.left() - 4.0, plot.top()), Align2::RIGHT_TOP, format_value(hi), font.clone(), AXIS_COLOR);
+    painter.text(Pos2::new(plot.left() - 4.0, plot.bottom()), Align2::RIGHT_BOTTOM, format_value(lo), font.clone(), AXIS_COLOR);
+    painter.text(Pos2::new(plot.left(), plot.bottom() + 2.0), Align2::LEFT_TOP, "1", font.clone(), AXIS_COLOR);
+    painter.text(Pos2::new(plot.right(), plot.bottom() + 2.0), Align2::RIGHT_TOP, n.to_string(), font.clone(), AXIS_COLOR);
+
+    for &s in separators {
+        let x = to_x(s as f64 - 0.5);
+        painter.line_segment([Pos2::new(x, plot.top()), Pos2::new(x, plot.bottom())], Stroke::new(1.0, Color32::DARK_GRAY));
+    }
+
+    for (_, color, values) in series {
+        let stroke = Stroke::new(1.5, *color);
+        let mut segment: Vec<Pos2> = Vec::new();
+
+        // a None (and the end of the series) closes the current segment
+        for (i, v) in values.iter().chain(std::iter::once(&None)).enumerate() {
+            if let Some(v) = v {
+                segment.push(Pos2::new(to_x(i as f64), to_y(*v)));
+                continue;
+            }
+            if segment.len() == 1 {
+                painter.circle_filled(segment[0], 2.0, *color);
+            } else if segment.len() > 1 {
+                painter.add(egui::Shape::line(segment.clone(), stroke));
+            }
+            segment.clear();
+        }
+    }
+
+    // values under the mouse
+    if let Some(pos) = response.hover_pos().filter(|p| plot.contains(*p)) {
+        let i = if n > 1 { (((pos.x - plot.left()) / plot.width()) * (n - 1) as f32).round() as usize } else { 0 };
+        let x = to_x(i as f64);
+        painter.line_segment([Pos2::new(x, plot.top()), Pos2::new(x, plot.bottom())], Stroke::new(1.0, AXIS_COLOR));
+
+        let mut text = format!("#{}", i + 1);
+        for (name, _, values) in series {
+            if let Some(Some(v)) = values.get(i) {
+                text += &format!("\n{name}: {}", format_value(*v));
+            }
+        }
+        painter.text(plot.left_top() + egui::vec2(4.0, 2.0), Align2::LEFT_TOP, text, font, ui.visuals().text_color());
+    }
+
+    ui.horizontal_wrapped(|ui| {
+        for (name, color, _) in series {
+            ui.colored_label(*color, format!("━ {name}"));
+        }
+    });
+}
+
+// Confusion matrix as a heatmap, cells coloured by their share of the true class (row)
+pub fn confusion_heatmap(ui: &mut Ui, labels: &[String], confusion: &[Vec<i64>]) {
+    let k = confusion.len();
+    if k == 0 {
+        return;
+    }
+    let margin = 24.0;
+    let cell = ((ui.available_width() - margin) / k as f32).clamp(14.0, 34.0);
+    let (response, painter) = ui.allocate_painter(egui::vec2(margin + cell * k as f32, margin + cell * k as f32), Sense::hover());
+    let origin = response.rect.min + egui::vec2(margin, margin);
+
+    let font = FontId::proportional(10.0);
+    let name = |i: usize| labels.get(i).cloned().unwrap_or_else(|| i.to_string());
+    for i in 0..k {
+        let offset = (i as f32 + 0.5) * cell;
+        painter.text(Pos2::new(origin.x + offset, origin.y - 4.0), Align2::CENTER_BOTTOM, name(i), font.clone(), AXIS_COLOR);
+        painter.text(Pos2::new(origin.x - 4.0, origin.y + offset), Align2::RIGHT_CENTER, name(i), font.clone(), AXIS_COLOR);
+    }
+
+    let mut hovered = None;
+    for (t, row) in confusion.iter().enumerate() {
+        let total: i64 = row.iter().sum();
+        for (p, &count) in row.iter().enumerate() {
+            let share = if total > 0 { count as f32 / total as f32 } else { 0.0 };
+            let rect = Rect::from_min_size(origin + egui::vec2(p as f32 * cell, t as f32 * cell), egui::vec2(cell, cell));
+
+            // hits green, mistakes red; sqrt so that rare mistakes stay visible
+            let base = if t == p { Color32::from_rgb(40, 150, 70) } else { Color32::from_rgb(200, 50, 40) };
+            let strength = share.sqrt();
+            painter.rect_filled(rect.shrink(0.5), 0.0, mix(Color32::WHITE, base, strength));
+
+            if count > 0 {
+                let text_color = if strength > 0.6 { Color32::WHITE } else { Color32::BLACK };
+                painter.text(rect.center(), Align2::CENTER_CENTER, count.to_string(), FontId::proportional(cell * 0.32), text_color);
+            }
+            if response.hover_pos().is_some_and(|pos| rect.contains(pos)) {
+                hovered = Some((t, p, count, share));
+            }
+        }
+    }
+
+    painter.text(response.rect.min, Align2::LEFT_TOP, "t\\p", font, AXIS_COLOR);
+    if let Some((t, p, count, share)) = hovered {
+        response.on_hover_text(format!("true {} -> predicted {}: {count} ({:.1}% of {})", name(t), name(p), 100.0 * share, name(t)));
+    }
+}
+
+fn mix(a: Color32, b: Color32, t: f32) -> Color32 {
+    let lerp = |x: u8, y: u8| (x as f32 + (y as f32 - x as f32) * t.clamp(0.0, 1.0)).round() as u8;
+    Color32::from_rgb(lerp(a.r(), b.r()), lerp(a.g(), b.g()), lerp(a.b(), b.b()))
+}
+
+fn format_value(v: f64) -> String {
+    if v.abs() >= 100.0 { format!("{v:.0}") } else if v.abs() >= 1.0 { format!("{v:.2}") } else { format!("{v:.3}") }
+}
\ No newline at end of file
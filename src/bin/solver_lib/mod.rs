@@ -1,4 +1,7 @@
 ﻿pub mod c_solver_app;
 pub mod c_painter_module;
 mod c_ai_module;
-mod f_utils;
\ No newline at end of file
+mod f_utils;
+mod f_plot;
+mod c_training_view;
+mod c_dataset_view;
\ No newline at end of file
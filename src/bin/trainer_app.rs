@@ -1,132 +1,110 @@
-﻿use std::{env, fs};
+﻿use std::{env, process};
 use std::path::Path;
-use tch::{nn, Device, Kind, Tensor};
-use tch::nn::{Module, OptimizerConfig};
+use tch::Device;
+use tch::vision::dataset::Dataset;
 use crate::shared_lib::c_trainer_config::TrainerConfig;
-use crate::shared_lib::f_ai_data::build_model;
+use crate::trainer_lib::f_idx::load_idx_dataset;
+use crate::trainer_lib::c_cli_args::{CliArgs, Command, USAGE};
+use crate::trainer_lib::c_run_dir::RunDir;
+use crate::trainer_lib::f_dataset::{import_dataset, verify_dataset};
+use crate::trainer_lib::f_mydata_idx::{export_mydata, import_mydata};
+use crate::trainer_lib::f_mydata_lint::lint_mydata;
+use crate::trainer_lib::f_eval::evaluate_checkpoint;
+use crate::trainer_lib::f_export::export_checkpoint;
+use crate::trainer_lib::f_finetune::finetune_on_mydata;
+use crate::trainer_lib::f_train_base::train_on_mnist;
 
 mod trainer_lib;
 mod shared_lib;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let config = TrainerConfig::new();
+    let args = match CliArgs::parse(env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            process::exit(2);
+        }
+    };
+    if let Command::Help = args.command {
+        println!("{USAGE}");
+        return Ok(());
+    }
+
+    let mut config = match &args.config_path {
+        Some(path) => TrainerConfig::load_from(Path::new(path))?,
+        None => TrainerConfig::new(),
+    };
+    for (key, value) in &args.overrides {
+        config.apply_override(key, value)?;
+    }
 
     let device = Device::cuda_if_available();
     println!("device: {:?}", device);
 
-
-    let m = tch::vision::mnist::load_dir(env::current_dir().unwrap().join(&config.data_dir))?;
-
-    let train_images = m.train_images.to_device(device);
-    let train_labels = m.train_labels.to_device(device);
-    let test_images = m.test_images.to_device(device);
-    let test_labels = m.test_labels.to_device(device);
-
-    let vs = nn::VarStore::new(device);
-    let root = &vs.root();
-    let model = build_model(root, &config);
-
-
-    let mut opt = nn::Adam::default().build(&vs, 1e-3)?;
-
-    for epoch in 1..= config.epoch {
-        let loss = model
-            .forward(&train_images)
-            .cross_entropy_for_logits(&train_labels);
-
-        opt.backward_step(&loss);
-
-        let acc = model
-            .forward(&test_images)
-            .accuracy_for_logits(&test_labels);
-
-        let loss_value = loss.double_value(&[]);
-        let acc_value  = acc.double_value(&[]);
-        println!(
-            "epoch {:3}/{:3} | loss {:8.5} | test acc {:5.2}%",
-            epoch,
-            config.epoch,
-            loss_value,
-            100.0 * acc_value
-        );
+    match args.command {
+        Command::All => {
+            let m = load_mnist(&config)?;
+            let mut run = RunDir::create(&config, "all")?;
+            train_on_mnist(&config, &m, device, None, &mut run)?;
+            if config.finetune.enabled {
+                finetune_on_mydata(&config, &config.out_path, &m, &mut run)?;
+            }
+        }
+        Command::Train { resume } => {
+            let m = load_mnist(&config)?;
+            let mut run = RunDir::create(&config, "train")?;
+            train_on_mnist(&config, &m, device, resume.as_deref(), &mut run)?;
+        }
+        Command::Finetune { from } => {
+            let m = load_mnist(&config)?;
+            let mut run = RunDir::create(&config, "finetune")?;
+            finetune_on_mydata(&config, from.as_deref().unwrap_or(&config.out_path), &m, &mut run)?;
+        }
+        Command::Eval { model, data } => {
+            let model = model.unwrap_or_else(|| config.inference_model_path().to_string());
+            let data = data.unwrap_or_else(|| config.data_dir.clone());
+            evaluate_checkpoint(&config, &model, &data, device)?;
+        }
+        Command::Export { model, out } => {
+            let model = model.unwrap_or_else(|| config.inference_model_path().to_string());
+            export_checkpoint(&config, &model, &out)?;
+        }
+        Command::DatasetVerify { data } => {
+            verify_dataset(data.as_deref().unwrap_or(&config.data_dir))?;
+        }
+        Command::DatasetImport { from, data } => {
+            let data = data.unwrap_or_else(|| config.data_dir.clone());
+            import_dataset(&from, &data)?;
+            verify_dataset(&data)?;
+        }
+        Command::MydataExport { out, data, test_fraction } => {
+            let data = data.unwrap_or_else(|| config.finetune.data_dir.clone());
+            export_mydata(&data, &out, test_fraction, config.seed)?;
+        }
+        Command::MydataImport { from, data } => {
+            import_mydata(&from, data.as_deref().unwrap_or(&config.finetune.data_dir))?;
+        }
+        Command::MydataLint { data, model, quarantine } => {
+            let data = data.unwrap_or_else(|| config.finetune.data_dir.clone());
+            let model = model.unwrap_or_else(|| config.inference_model_path().to_string());
+            lint_mydata(&config, &data, &model, quarantine.as_deref(), device)?;
+        }
+        Command::Help => {}
     }
-    vs.save(&config.out_path)?;
-    println!("saved weights -> {}", config.out_path);
-    finetune_on_mydata(&config);
 
     return Ok(())
 }
 
-fn finetune_on_mydata(config: &TrainerConfig){
-    let device = Device::cuda_if_available();
-    let mut vs = nn::VarStore::new(device);
-
-    let model = build_model(&vs.root(), &config);
-
-    vs.load(&config.out_path).expect("load weights");
-
-    let (x_cpu, y_cpu) = load_mydata("mydata", config);
-    let x = x_cpu.to_device(device);
-    let y = y_cpu.to_device(device);
-
-    let mut opt = nn::Adam::default().build(&vs, 1e-4).unwrap();
-
-    for epoch in 1..=350 {
-        let logits = model.forward(&x);
-        let loss = logits.cross_entropy_for_logits(&y);
-        opt.backward_step(&loss);
+fn load_mnist(config: &TrainerConfig) -> Result<Dataset, Box<dyn std::error::Error>> {
+    let m = load_idx_dataset(env::current_dir().unwrap().join(&config.data_dir), config.data_transposed)?;
 
-        let acc = logits.accuracy_for_logits(&y);
-        println!(
-            "ft epoch {:02} | loss {:7.4} | acc {:5.2}%",
-            epoch,
-            loss.double_value(&[]),
-            100.0 * acc.double_value(&[])
-        );
+    let pixels = m.train_images.size()[1];
+    if pixels != config.image_dim {
+        return Err(format!("{}: images have {pixels} pixels, config image_dim is {}", config.data_dir, config.image_dim).into());
     }
-
-    vs.save(&config.out_path).expect("save finetuned");
-}
-
-
-pub fn load_mydata(dir: impl AsRef<Path>, config: &TrainerConfig) -> (Tensor, Tensor) {
-    let dir = dir.as_ref();
-
-    let mut images: Vec<f32> = Vec::new();
-    let mut labels: Vec<i64> = Vec::new();
-
-    for label in 0..10i64 {
-        let sub = dir.join(label.to_string());
-        let Ok(rd) = fs::read_dir(&sub) else { continue; };
-
-        for entry in rd.flatten() {
-            let path = entry.path();
-            if path.extension().and_then(|s| s.to_str()) != Some("bin") {
-                continue;
-            }
-            let Ok(bytes) = fs::read(&path) else { continue; };
-            if bytes.len() != 28 * 28 {
-                continue;
-            }
-
-            // u8 -> f32 (0..1)
-            for &b in &bytes {
-                images.push((b as f32) / 255.0);
-            }
-            labels.push(label);
-        }
+    if m.labels > config.labels {
+        return Err(format!("{}: labels go up to {}, config labels is {}", config.data_dir, m.labels - 1, config.labels).into());
     }
-
-    let n = labels.len() as i64;
-    println!("Loaded mydata samples: {n}");
-
-    // CNN: [N, 1, 28, 28]
-    let x = Tensor::from_slice(&images)
-        .to_kind(Kind::Float)
-        .view([n, config.image_dim]);
-
-    let y = Tensor::from_slice(&labels).to_kind(Kind::Int64);
-
-    (x, y)
-}
-
+    println!("{}: {} train / {} test images", config.data_dir, m.train_labels.size()[0], m.test_labels.size()[0]);
+    Ok(m)
+}
\ No newline at end of file
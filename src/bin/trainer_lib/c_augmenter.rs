@@ -0,0 +1,114 @@
+﻿use tch::{Kind, Tensor};
+use crate::shared_lib::c_augment_config::AugmentConfig;
+use crate::shared_lib::c_model_manifest::ModelSpec;
+use crate::shared_lib::f_ai_data::{image_side, input_shape};
+
+// Applies AugmentConfig to whole batches on the batch's device. All randomness comes from
+// the libtorch RNG, so runs stay reproducible with `seed`.
+pub struct Augmenter {
+    config: AugmentConfig,
+    spec: ModelSpec,
+    side: i64,
+}
+
+impl Augmenter {
+    pub fn new(config: &AugmentConfig, spec: &ModelSpec) -> Self {
+        Self{
+            config: config.clone(),
+            spec: spec.clone(),
+            side: image_side(spec),
+        }
+    }
+
+    // x in model input layout, returns the same layout
+    pub fn apply(&self, x: &Tensor) -> Tensor {
+        let c = &self.config;
+        let n = x.size()[0];
+        if !c.enabled || n == 0 {
+            return x.shallow_clone();
+        }
+
+        let mut img = x.view([n, 1, self.side, self.side]);
+
+        let affine = c.rotation_deg != 0.0 || c.scale != 0.0 || c.shear_deg != 0.0 || c.translate != 0.0;
+        if affine || c.elastic_alpha > 0.0 {
+            img = self.warp(&img);
+        }
+        if c.morphology_prob > 0.0 {
+            img = self.morphology(&img);
+        }
+        if c.noise_std > 0.0 {
+            img = (&img + img.randn_like() * c.noise_std).clamp(0.0, 1.0);
+        }
+
+        img.view(input_shape(&self.spec, n).as_slice())
+    }
+
+    // Random affine transform plus elastic displacement, sampled through one grid
+    fn warp(&self, img: &Tensor) -> Tensor {
+        let c = &self.config;
+        let n = img.size()[0];
+        let opts = (Kind::Float, img.device());
+        // uniform in -1..1, one value per image
+        let sym = || Tensor::rand([n], opts) * 2.0 - 1.0;
+
+        let angle = sym() * c.rotation_deg.to_radians();
+        let shear = (sym() * c.shear_deg.to_radians()).tan();
+        let scale = sym() * c.scale + 1.0;
+        // grid coordinates span -1..1, i.e. two image sides
+        let tx = sym() * (2.0 * c.translate);
+        let ty = sym() * (2.0 * c.translate);
+
+        // rotation * shear * scale
+        let (cos, sin) = (angle.cos(), angle.sin());
+        let a = &cos * &scale;
+        let b = (&cos * &shear - &sin) * &scale;
+        let d = &sin * &scale;
+        let e = (&sin * &shear + &cos) * &scale;
+        let theta = Tensor::stack(&[Tensor::stack(&[a, b, tx], 1), Tensor::stack(&[d, e, ty], 1)], 1);
+
+        let mut grid = Tensor::affine_grid_generator(&theta, [n, 1, self.side, self.side], false);
+        if c.elastic_alpha > 0.0 {
+            // pixels -> grid units
+            let disp = self.smooth_noise(n, img) * (c.elastic_alpha * 2.0 / self.side as f64);
+            grid += disp.permute([0, 2, 3, 1]);
+        }
+
+        // bilinear, zero padding
+        img.grid_sampler(&grid, 0, 0, false)
+    }
+
+    // [n, 2, side, side] random field, gaussian-smoothed and rescaled to -1..1
+    fn smooth_noise(&self, n: i64, img: &Tensor) -> Tensor {
+        let sigma = self.config.elastic_sigma.max(0.5);
+        let radius = (2.0 * sigma).ceil() as i64;
+        let k = 2 * radius + 1;
+
+        let opts = (Kind::Float, img.device());
+        let r = Tensor::arange(k, opts) - radius as f64;
+        let g = (-(&r * &r) / (2.0 * sigma * sigma)).exp();
+        let g = &g / g.sum(Kind::Float);
+        let kernel = g.view([k, 1]).matmul(&g.view([1, k])).view([1, 1, k, k]).repeat([2, 1, 1, 1]);
+
+        let field = Tensor::rand([n, 2, self.side, self.side], opts) * 2.0 - 1.0;
+        let field = field.conv2d(&kernel, None::<Tensor>, [1, 1], [radius, radius], [1, 1], 2);
+
+        // smoothing shrinks the field, bring the largest displacement back to 1
+        let peak = field.abs().amax([1, 2, 3], true).clamp_min(1e-6);
+        field / peak
+    }
+
+    // Per image: dilate, erode or keep
+    fn morphology(&self, img: &Tensor) -> Tensor {
+        let n = img.size()[0];
+        let p = self.config.morphology_prob.clamp(0.0, 1.0);
+        let u = Tensor::rand([n, 1, 1, 1], (Kind::Float, img.device()));
+
+        let dilated = img.max_pool2d([3, 3], [1, 1], [1, 1], [1, 1], false);
+        let eroded = -(-img).max_pool2d([3, 3], [1, 1], [1, 1], [1, 1], false);
+
+        let thicker = u.lt(p / 2.0);
+        let thinner = u.ge(p / 2.0).logical_and(&u.lt(p));
+        dilated.where_self(&thicker, &eroded.where_self(&thinner, img))
+    }
+}
\ No newline at end of file
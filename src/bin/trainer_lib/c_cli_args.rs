@@ -0,0 +1,150 @@
+﻿pub const USAGE: &str = "\
+usage: trainer_app [command] [options] [--<config field> <value> ...]
+
+commands:
+  (none)                               train on MNIST, then fine-tune if enabled
+  train [--resume <checkpoint>]        train on MNIST only, optionally continuing a saved training state
+  finetune [--from <ckpt>]             fine-tune a checkpoint (default: out_path) on mydata
+  eval [--model <ckpt>] [--data <dir>] accuracy of a checkpoint on an MNIST dir or a mydata-style dir
+  export [--model <ckpt>] --out <path> copy weights + manifest, format by extension (.ot, .safetensors, .npz)
+  dataset verify [--data <dir>]        check the MNIST files in data_dir (sha256, IDX headers, sample counts)
+  dataset import --from <dir> [--data <dir>]
+                                       copy / decompress MNIST files from a local folder into data_dir
+  mydata export --out <dir> [--data <dir>] [--test-fraction <f>]
+                                       pack mydata (default: finetune.data_dir) into MNIST-style IDX files
+  mydata import --from <dir> [--data <dir>]
+                                       unpack IDX files into mydata samples
+  mydata lint [--data <dir>] [--model <ckpt>] [--quarantine <dir>]
+                                       find duplicates, blank images and samples the model calls mislabeled,
+                                       optionally move them to a quarantine folder
+
+options:
+  --config <path>                      config file (default: ./config.json)
+  --<field> <value>                    override any config.json field for this run,
+                                       nested fields with dots: --optimizer.lr 0.01 --finetune.enabled false
+  --help                               show this text";
+
+pub enum Command {
+    All,
+    Train { resume: Option<String> },
+    Finetune { from: Option<String> },
+    Eval { model: Option<String>, data: Option<String> },
+    Export { model: Option<String>, out: String },
+    DatasetVerify { data: Option<String> },
+    DatasetImport { from: String, data: Option<String> },
+    MydataExport { out: String, data: Option<String>, test_fraction: f64 },
+    MydataImport { from: String, data: Option<String> },
+    MydataLint { data: Option<String>, model: Option<String>, quarantine: Option<String> },
+    Help,
+}
+
+pub struct CliArgs {
+    pub command: Command,
+    pub config_path: Option<String>,
+    // (field, value) pairs applied on top of the loaded config
+    pub overrides: Vec<(String, String)>,
+}
+
+impl CliArgs {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.into_iter().peekable();
+
+        let mut positional = || match args.peek() {
+            Some(a) if !a.starts_with("--") => args.next(),
+            _ => None,
+        };
+        let command_name = positional();
+        // `dataset` and `mydata` have their own sub-commands
+        let sub_command = match command_name.as_deref() {
+            Some("dataset") | Some("mydata") => positional(),
+            _ => None,
+        };
+
+        let mut config_path = None;
+        let mut overrides = Vec::new();
+        let mut options: Vec<(String, String)> = Vec::new();
+        let mut help = false;
+
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                return Err(format!("unexpected argument `{arg}`"));
+            };
+            if name == "help" {
+                help = true;
+                continue;
+            }
+
+            // --name value or --name=value
+            let (name, value) = match name.split_once('=') {
+                Some((n, v)) => (n.to_string(), v.to_string()),
+                None => {
+                    let value = args.next().ok_or_else(|| format!("missing value for --{name}"))?;
+                    (name.to_string(), value)
+                }
+            };
+
+            match name.as_str() {
+                "config" => config_path = Some(value),
+                "from" | "model" | "data" | "out" | "resume" | "test-fraction" | "quarantine" => options.push((name, value)),
+                _ => overrides.push((name, value)),
+            }
+        }
+
+        let mut take = |key: &str| {
+            options.iter().position(|(n, _)| n == key).map(|i| options.remove(i).1)
+        };
+
+        let command = match command_name.as_deref() {
+            _ if help => Command::Help,
+            None => Command::All,
+            Some("train") => Command::Train { resume: take("resume") },
+            Some("finetune") => Command::Finetune { from: take("from") },
+            Some("eval") => Command::Eval { model: take("model"), data: take("data") },
+            Some("export") => Command::Export {
+                model: take("model"),
+                out: take("out").ok_or("export needs --out <path>")?,
+            },
+            Some("dataset") => match sub_command.as_deref() {
+                Some("verify") => Command::DatasetVerify { data: take("data") },
+                Some("import") => Command::DatasetImport {
+                    from: take("from").ok_or("dataset import needs --from <dir>")?,
+                    data: take("data"),
+                },
+                Some(other) => return Err(format!("unknown dataset command `{other}`")),
+                None => return Err("dataset needs a command: verify or import".to_string()),
+            },
+            Some("mydata") => match sub_command.as_deref() {
+                Some("export") => Command::MydataExport {
+                    out: take("out").ok_or("mydata export needs --out <dir>")?,
+                    data: take("data"),
+                    test_fraction: match take("test-fraction") {
+                        Some(f) => f.parse().map_err(|_| format!("--test-fraction: `{f}` is not a number"))?,
+                        None => 0.0,
+                    },
+                },
+                Some("import") => Command::MydataImport {
+                    from: take("from").ok_or("mydata import needs --from <dir>")?,
+                    data: take("data"),
+                },
+                Some("lint") => Command::MydataLint {
+                    data: take("data"),
+                    model: take("model"),
+                    quarantine: take("quarantine"),
+                },
+                Some(other) => return Err(format!("unknown mydata command `{other}`")),
+                None => return Err("mydata needs a command: export, import or lint".to_string()),
+            },
+            Some(other) => return Err(format!("unknown command `{other}`")),
+        };
+
+        if let Some((name, _)) = options.first() {
+            return Err(format!("--{name} is not an option of this command"));
+        }
+
+        Ok(Self{
+            command,
+            config_path,
+            overrides,
+        })
+    }
+}
\ No newline at end of file
@@ -0,0 +1,43 @@
+﻿use serde::{Deserialize, Serialize};
+
+// Tracks the best validation metric and how many epochs passed without improving it
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct EarlyStopping {
+    pub patience: usize,
+    pub best: Option<f64>,
+    pub best_epoch: usize,
+    pub stale_epochs: usize,
+}
+
+impl EarlyStopping {
+    pub fn new(patience: usize) -> Self {
+        Self{
+            patience,
+            best: None,
+            best_epoch: 0,
+            stale_epochs: 0,
+        }
+    }
+
+    // Returns true when `metric` (higher is better) is a new best
+    pub fn update(&mut self, epoch: usize, metric: f64) -> bool {
+        let improved = match self.best {
+            Some(best) => metric > best,
+            None => true,
+        };
+
+        if improved {
+            self.best = Some(metric);
+            self.best_epoch = epoch;
+            self.stale_epochs = 0;
+        } else {
+            self.stale_epochs += 1;
+        }
+        improved
+    }
+
+    // patience 0 disables early stopping
+    pub fn should_stop(&self) -> bool {
+        self.patience > 0 && self.stale_epochs >= self.patience
+    }
+}
\ No newline at end of file
@@ -0,0 +1,222 @@
+﻿use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use tch::{Device, Kind, Tensor};
+use tch::nn::ModuleT;
+
+const TOP_K: [usize; 3] = [1, 2, 3];
+// misclassified samples kept in the report, most confident mistakes first
+const WORST_N: usize = 20;
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ClassMetrics {
+    pub label: String,
+    // true samples of this class
+    pub support: i64,
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Misclassified {
+    // position in the evaluated set (MNIST test index, or load order for mydata)
+    pub index: i64,
+    pub label: String,
+    pub predicted: String,
+    // probability of the predicted and of the true class
+    pub confidence: f64,
+    pub label_prob: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct EvalReport {
+    pub model: String,
+    pub data: String,
+    pub samples: i64,
+    pub accuracy: f64,
+    pub top_k_accuracy: BTreeMap<usize, f64>,
+    // confusion[true][predicted]
+    pub confusion: Vec<Vec<i64>>,
+    pub per_class: Vec<ClassMetrics>,
+    pub worst: Vec<Misclassified>,
+}
+
+impl EvalReport {
+    pub fn compute(
+        model: &impl ModuleT,
+        images: &Tensor,
+        labels: &Tensor,
+        label_names: &[String],
+        device: Device,
+        model_path: &str,
+        data: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let probs = predict_probs(model, images, device, 1024);
+        let size = probs.size();
+        let (n, classes) = (size[0], size[1] as usize);
+        let probs = Vec::<f32>::try_from(probs.flatten(0, -1))?;
+        let labels = Vec::<i64>::try_from(labels.to_kind(Kind::Int64))?;
+
+        let name = |c: usize| label_names.get(c).cloned().unwrap_or_else(|| c.to_string());
+
+        let mut confusion = vec![vec![0i64; classes]; classes];
+        let mut top_k_hits = [0i64; TOP_K.len()];
+        let mut worst = Vec::new();
+
+        for (i, &label) in labels.iter().enumerate() {
+            let p = &probs[i * classes..(i + 1) * classes];
+            let label = label as usize;
+            let predicted = (0..classes).fold(0, |best, c| if p[c] > p[best] { c } else { best });
+            confusion[label][predicted] += 1;
+
+            // classes ranked above the true one
+            let rank = p.iter().filter(|&&q| q > p[label]).count();
+            for (hits, k) in top_k_hits.iter_mut().zip(TOP_K) {
+                if rank < k {
+                    *hits += 1;
+                }
+            }
+
+            if predicted != label {
+                worst.push(Misclassified {
+                    index: i as i64,
+                    label: name(label),
+                    predicted: name(predicted),
+                    confidence: p[predicted] as f64,
+                    label_prob: p[label] as f64,
+                });
+            }
+        }
+        worst.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+        worst.truncate(WORST_N);
+
+        let per_class = (0..classes)
+            .map(|c| {
+                let tp = confusion[c][c] as f64;
+                let support: i64 = confusion[c].iter().sum();
+                let predicted: i64 = confusion.iter().map(|row| row[c]).sum();
+                let precision = if predicted > 0 { tp / predicted as f64 } else { 0.0 };
+                let recall = if support > 0 { tp / support as f64 } else { 0.0 };
+                let f1 = if precision + recall > 0.0 { 2.0 * precision * recall / (precision + recall) } else { 0.0 };
+                ClassMetrics { label: name(c), support, precision, recall, f1 }
+            })
+            .collect();
+
+        let rate = |hits: i64| if n > 0 { hits as f64 / n as f64 } else { 0.0 };
+        Ok(Self{
+            model: model_path.to_string(),
+            data: data.to_string(),
+            samples: n,
+            accuracy: rate(top_k_hits[0]),
+            top_k_accuracy: TOP_K.iter().zip(top_k_hits).map(|(&k, hits)| (k, rate(hits))).collect(),
+            confusion,
+            per_class,
+            worst,
+        })
+    }
+
+    pub fn print(&self) {
+        println!("confusion matrix (rows: true, cols: predicted)");
+        print!("     ");
+        for c in &self.per_class {
+            print!("{:>6}", c.label);
+        }
+        println!();
+        for (c, row) in self.per_class.iter().zip(&self.confusion) {
+            print!("{:>5}", c.label);
+            for v in row {
+                print!("{v:>6}");
+            }
+            println!();
+        }
+
+        println!("class  precision  recall      f1  support");
+        for c in &self.per_class {
+            println!(
+                "{:>5}  {:8.2}%  {:5.2}%  {:5.2}%  {:7}",
+                c.label,
+                100.0 * c.precision,
+                100.0 * c.recall,
+                100.0 * c.f1,
+                c.support
+            );
+        }
+
+        let top_k: Vec<String> = self
+            .top_k_accuracy
+            .iter()
+            .map(|(k, acc)| format!("top-{k} {:5.2}%", 100.0 * acc))
+            .collect();
+        println!("{}", top_k.join(" | "));
+
+        if !self.worst.is_empty() {
+            println!("most confident mistakes (index: true -> predicted):");
+            for w in self.worst.iter().take(10) {
+                println!(
+                    "  #{:<6} {} -> {} ({:5.2}%, true class {:5.2}%)",
+                    w.index,
+                    w.label,
+                    w.predicted,
+                    100.0 * w.confidence,
+                    100.0 * w.label_prob
+                );
+            }
+        }
+    }
+
+    // models/mnist.ot + "test" -> models/mnist_eval_test.json, _confusion_test.csv, _classes_test.csv
+    pub fn path_for(weights: impl AsRef<Path>, kind: &str, tag: &str, ext: &str) -> PathBuf {
+        let weights = weights.as_ref();
+        let stem = weights.file_stem().and_then(|s| s.to_str()).unwrap_or("model");
+        weights.with_file_name(format!("{stem}_{kind}_{tag}.{ext}"))
+    }
+
+    // Writes the report next to the weights, returns the JSON path
+    pub fn save_for(&self, weights: impl AsRef<Path>, tag: &str) -> io::Result<PathBuf> {
+        let weights = weights.as_ref();
+
+        let json = Self::path_for(weights, "eval", tag, "json");
+        let mut w = BufWriter::new(fs::File::create(&json)?);
+        serde_json::to_writer_pretty(&mut w, self)?;
+        w.flush()?;
+
+        let mut w = BufWriter::new(fs::File::create(Self::path_for(weights, "confusion", tag, "csv"))?);
+        let names: Vec<&str> = self.per_class.iter().map(|c| c.label.as_str()).collect();
+        writeln!(w, "true\\predicted,{}", names.join(","))?;
+        for (name, row) in names.iter().zip(&self.confusion) {
+            let row: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+            writeln!(w, "{name},{}", row.join(","))?;
+        }
+        w.flush()?;
+
+        let mut w = BufWriter::new(fs::File::create(Self::path_for(weights, "classes", tag, "csv"))?);
+        writeln!(w, "label,precision,recall,f1,support")?;
+        for c in &self.per_class {
+            writeln!(w, "{},{:.6},{:.6},{:.6},{}", c.label, c.precision, c.recall, c.f1, c.support)?;
+        }
+        w.flush()?;
+
+        Ok(json)
+    }
+}
+
+// Softmax outputs for the whole set, in batches, collected on the CPU
+pub fn predict_probs(model: &impl ModuleT, images: &Tensor, device: Device, batch_size: i64) -> Tensor {
+    let n = images.size()[0];
+    let mut parts = Vec::new();
+
+    tch::no_grad(|| {
+        let mut start = 0;
+        while start < n {
+            let len = batch_size.min(n - start);
+            let x = images.narrow(0, start, len).to_device(device);
+            parts.push(model.forward_t(&x, false).softmax(-1, Kind::Float).to_device(Device::Cpu));
+            start += len;
+        }
+    });
+
+    Tensor::cat(&parts, 0)
+}
\ No newline at end of file
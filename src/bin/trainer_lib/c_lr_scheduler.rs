@@ -0,0 +1,93 @@
+﻿use std::f64::consts::PI;
+use serde::{Deserialize, Serialize};
+use crate::shared_lib::c_optimizer_settings::{LrSchedule, OptimizerSettings};
+
+// The part of the scheduler that changes during a run, saved in resume checkpoints
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SchedulerProgress {
+    pub step: usize,
+
+    // reduce-on-plateau state
+    pub plateau_scale: f64,
+    pub plateau_best: Option<f64>,
+    pub plateau_stale: usize,
+}
+
+// Computes the learning rate for every optimizer step of a run
+pub struct LrScheduler {
+    settings: OptimizerSettings,
+    steps_per_epoch: usize,
+    total_steps: usize,
+    progress: SchedulerProgress,
+}
+
+impl LrScheduler {
+    pub fn new(settings: &OptimizerSettings, steps_per_epoch: usize, epochs: usize) -> Self {
+        Self{
+            settings: settings.clone(),
+            steps_per_epoch: steps_per_epoch.max(1),
+            total_steps: (steps_per_epoch * epochs).max(1),
+            progress: SchedulerProgress {
+                step: 0,
+                plateau_scale: 1.0,
+                plateau_best: None,
+                plateau_stale: 0,
+            },
+        }
+    }
+
+    pub fn progress(&self) -> &SchedulerProgress {
+        &self.progress
+    }
+
+    pub fn restore(&mut self, progress: SchedulerProgress) {
+        self.progress = progress;
+    }
+
+    pub fn current_lr(&self) -> f64 {
+        let base = self.settings.lr;
+        let epoch = self.progress.step / self.steps_per_epoch;
+
+        let lr = match &self.settings.schedule {
+            LrSchedule::Constant => base,
+            LrSchedule::Step { every_epochs, gamma } => {
+                base * gamma.powi((epoch / (*every_epochs).max(1)) as i32)
+            }
+            LrSchedule::Cosine { min_lr } => {
+                let t = (self.progress.step as f64 / self.total_steps as f64).min(1.0);
+                min_lr + (base - min_lr) * 0.5 * (1.0 + (PI * t).cos())
+            }
+            LrSchedule::Plateau { min_lr, .. } => (base * self.progress.plateau_scale).max(*min_lr),
+        };
+
+        let warmup = self.settings.warmup_steps;
+        if warmup > 0 && self.progress.step < warmup {
+            lr * (self.progress.step + 1) as f64 / warmup as f64
+        } else {
+            lr
+        }
+    }
+
+    // Call after every optimizer step
+    pub fn step(&mut self) {
+        self.progress.step += 1;
+    }
+
+    // Call once per epoch with the validation metric (higher is better), if there is one
+    pub fn end_epoch(&mut self, metric: Option<f64>) {
+        let LrSchedule::Plateau { patience, factor, .. } = self.settings.schedule else { return; };
+        let Some(metric) = metric else { return; };
+
+        if self.progress.plateau_best.is_none_or(|best| metric > best) {
+            self.progress.plateau_best = Some(metric);
+            self.progress.plateau_stale = 0;
+        } else {
+            self.progress.plateau_stale += 1;
+            if self.progress.plateau_stale >= patience.max(1) {
+                self.progress.plateau_scale *= factor;
+                self.progress.plateau_stale = 0;
+                println!("val plateau: lr -> {:.2e}", self.current_lr());
+            }
+        }
+    }
+}
\ No newline at end of file
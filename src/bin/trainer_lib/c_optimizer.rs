@@ -0,0 +1,140 @@
+﻿use std::collections::HashMap;
+use tch::{nn, Kind, Tensor};
+use crate::shared_lib::c_optimizer_settings::{OptimizerKind, OptimizerSettings};
+
+// SGD / Adam / AdamW with the same update rules as torch.optim, but with state that can be
+// saved and restored (libtorch's optimizers don't expose their moments through tch)
+pub struct Optimizer {
+    settings: OptimizerSettings,
+    // trainable variables by VarStore name, frozen ones are left out
+    params: Vec<(String, Tensor)>,
+    // "<param>.momentum", "<param>.exp_avg", "<param>.exp_avg_sq"
+    state: HashMap<String, Tensor>,
+    steps: i64,
+    lr: f64,
+}
+
+impl Optimizer {
+    pub fn new(vs: &nn::VarStore, settings: &OptimizerSettings) -> Self {
+        let mut params: Vec<(String, Tensor)> = vs
+            .variables()
+            .into_iter()
+            .filter(|(_, t)| t.requires_grad())
+            .collect();
+        params.sort_by(|a, b| a.0.cmp(&b.0));
+
+        Self{
+            settings: settings.clone(),
+            params,
+            state: HashMap::new(),
+            steps: 0,
+            lr: settings.lr,
+        }
+    }
+
+    pub fn set_lr(&mut self, lr: f64) {
+        self.lr = lr;
+    }
+
+    pub fn backward_step(&mut self, loss: &Tensor) {
+        for (_, p) in self.params.iter_mut() {
+            p.zero_grad();
+        }
+        loss.backward();
+
+        self.steps += 1;
+        let params = std::mem::take(&mut self.params);
+        tch::no_grad(|| {
+            for (name, p) in &params {
+                let grad = p.grad();
+                if !grad.defined() {
+                    continue;
+                }
+                match self.settings.kind {
+                    OptimizerKind::Sgd => self.sgd_update(name, p, &grad),
+                    OptimizerKind::Adam => self.adam_update(name, p, &grad, false),
+                    OptimizerKind::AdamW => self.adam_update(name, p, &grad, true),
+                }
+            }
+        });
+        self.params = params;
+    }
+
+    fn sgd_update(&mut self, name: &str, p: &Tensor, grad: &Tensor) {
+        let s = &self.settings;
+        let mut g = if s.weight_decay != 0.0 { grad + p * s.weight_decay } else { grad.shallow_clone() };
+
+        if s.momentum != 0.0 {
+            let buf = self.state.entry(format!("{name}.momentum")).or_insert_with(|| g.zeros_like());
+            *buf *= s.momentum;
+            *buf += &g;
+            g = if s.nesterov { g + &*buf * s.momentum } else { buf.shallow_clone() };
+        }
+
+        let mut p = p.shallow_clone();
+        p -= g * self.lr;
+    }
+
+    fn adam_update(&mut self, name: &str, p: &Tensor, grad: &Tensor, decoupled: bool) {
+        const BETA1: f64 = 0.9;
+        const BETA2: f64 = 0.999;
+        const EPS: f64 = 1e-8;
+
+        let wd = self.settings.weight_decay;
+        let mut p = p.shallow_clone();
+        let mut g = grad.shallow_clone();
+        if wd != 0.0 {
+            if decoupled {
+                p *= 1.0 - self.lr * wd;
+            } else {
+                g = grad + &p * wd;
+            }
+        }
+
+        let m = self.state.entry(format!("{name}.exp_avg")).or_insert_with(|| g.zeros_like());
+        *m *= BETA1;
+        *m += &g * (1.0 - BETA1);
+        let m_hat = &*m / (1.0 - BETA1.powi(self.steps as i32));
+
+        let v = self.state.entry(format!("{name}.exp_avg_sq")).or_insert_with(|| g.zeros_like());
+        *v *= BETA2;
+        *v += (&g * &g) * (1.0 - BETA2);
+        let v_hat = &*v / (1.0 - BETA2.powi(self.steps as i32));
+
+        p -= m_hat / (v_hat.sqrt() + EPS) * self.lr;
+    }
+
+    // Moment buffers and the step counter, named for Tensor::save_multi
+    pub fn state_tensors(&self) -> Vec<(String, Tensor)> {
+        let mut named: Vec<(String, Tensor)> = self
+            .state
+            .iter()
+            .map(|(k, t)| (k.clone(), t.shallow_clone()))
+            .collect();
+        named.sort_by(|a, b| a.0.cmp(&b.0));
+        named.push(("steps".to_string(), Tensor::from(self.steps)));
+        named
+    }
+
+    pub fn load_state(&mut self, named: HashMap<String, Tensor>) -> Result<(), String> {
+        let device = self.params.first().map(|(_, p)| p.device());
+
+        self.state.clear();
+        for (key, t) in named {
+            if key == "steps" {
+                self.steps = t.to_kind(Kind::Int64).int64_value(&[]);
+                continue;
+            }
+            let known = self.params.iter().any(|(name, _)| key.starts_with(&format!("{name}.")));
+            if !known {
+                return Err(format!("optimizer state `{key}` does not match any trainable variable"));
+            }
+            let t = match device {
+                Some(d) => t.to_device(d),
+                None => t,
+            };
+            self.state.insert(key, t);
+        }
+        Ok(())
+    }
+}
\ No newline at end of file
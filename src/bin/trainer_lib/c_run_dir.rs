@@ -0,0 +1,113 @@
+﻿use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::shared_lib::c_epoch_metrics::EpochMetrics;
+use crate::shared_lib::c_model_manifest::ModelManifest;
+use crate::shared_lib::c_trainer_config::TrainerConfig;
+
+const CSV_HEADER: &str = "stage,epoch,loss,lr,train_acc,val_acc,test_acc,seconds";
+
+// runs/<UTC timestamp>_<command>/ with the resolved config, metrics log, final weights and reports
+pub struct RunDir {
+    pub path: PathBuf,
+    csv: BufWriter<fs::File>,
+    jsonl: BufWriter<fs::File>,
+}
+
+impl RunDir {
+    pub fn create(config: &TrainerConfig, command: &str) -> io::Result<Self> {
+        let root = Path::new(&config.runs_dir);
+        let stamp = utc_timestamp();
+
+        // two runs started in the same second get a suffix
+        let mut path = root.join(format!("{stamp}_{command}"));
+        let mut n = 2;
+        while path.exists() {
+            path = root.join(format!("{stamp}_{command}-{n}"));
+            n += 1;
+        }
+        fs::create_dir_all(&path)?;
+
+        let mut w = BufWriter::new(fs::File::create(path.join("config.json"))?);
+        serde_json::to_writer_pretty(&mut w, config)?;
+        w.flush()?;
+
+        let mut csv = BufWriter::new(fs::File::create(path.join("metrics.csv"))?);
+        writeln!(csv, "{CSV_HEADER}")?;
+        csv.flush()?;
+        let jsonl = BufWriter::new(fs::File::create(path.join("metrics.jsonl"))?);
+
+        println!("run dir: {}", path.display());
+        Ok(Self{
+            path,
+            csv,
+            jsonl,
+        })
+    }
+
+    // Appends one row to both logs, flushed right away so an interrupted run keeps its history
+    pub fn log(&mut self, m: &EpochMetrics) -> io::Result<()> {
+        let opt = |v: Option<f64>| v.map(|v| format!("{v:.6}")).unwrap_or_default();
+        writeln!(
+            self.csv,
+            "{},{},{},{},{},{},{},{:.3}",
+            m.stage,
+            m.epoch,
+            opt(m.loss),
+            opt(m.lr),
+            opt(m.train_acc),
+            opt(m.val_acc),
+            opt(m.test_acc),
+            m.seconds
+        )?;
+        self.csv.flush()?;
+
+        serde_json::to_writer(&mut self.jsonl, m)?;
+        writeln!(self.jsonl)?;
+        self.jsonl.flush()
+    }
+
+    // Where a copy of `weights` lives inside the run dir
+    pub fn model_path(&self, weights: impl AsRef<Path>) -> PathBuf {
+        let name = weights.as_ref().file_name().map(|n| n.to_os_string()).unwrap_or_else(|| "model.ot".into());
+        self.path.join(name)
+    }
+
+    // Copies the weights and their manifest into the run dir, returns the copied weights path
+    pub fn save_model(&self, weights: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let weights = weights.as_ref();
+        let target = self.model_path(weights);
+        fs::copy(weights, &target)?;
+
+        let manifest = ModelManifest::path_for(weights);
+        if manifest.exists() {
+            fs::copy(&manifest, ModelManifest::path_for(&target))?;
+        }
+        Ok(target)
+    }
+}
+
+// YYYYmmdd-HHMMSS in UTC
+fn utc_timestamp() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0) as i64;
+    let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+
+    // days since 1970-01-01 -> civil date
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
\ No newline at end of file
@@ -0,0 +1,122 @@
+﻿use std::collections::HashMap;
+use std::fs;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use tch::{nn, Tensor};
+use crate::shared_lib::c_model_manifest::ModelSpec;
+use crate::trainer_lib::c_early_stopping::EarlyStopping;
+use crate::trainer_lib::c_lr_scheduler::SchedulerProgress;
+use crate::trainer_lib::c_optimizer::Optimizer;
+
+// 2: the epoch is also stored with the tensors
+pub const TRAINING_STATE_VERSION: u32 = 2;
+const EPOCH_KEY: &str = "state/epoch";
+
+// Everything needed to continue an interrupted run. Tensors (weights under "model/", optimizer
+// moments under "optim/", the epoch as "state/epoch") go to `path`, the rest to the JSON file next to it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TrainingState {
+    pub format_version: u32,
+    // last finished epoch
+    pub epoch: usize,
+    pub seed: u64,
+    pub model: ModelSpec,
+    pub stopping: EarlyStopping,
+    pub scheduler: SchedulerProgress,
+}
+
+impl TrainingState {
+    pub fn json_path(path: impl AsRef<Path>) -> PathBuf {
+        path.as_ref().with_extension("json")
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>, vs: &nn::VarStore, opt: &Optimizer) -> Result<(), Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut named: Vec<(String, Tensor)> = vs
+            .variables()
+            .into_iter()
+            .map(|(k, t)| (format!("model/{k}"), t))
+            .collect();
+        named.extend(opt.state_tensors().into_iter().map(|(k, t)| (format!("optim/{k}"), t)));
+        named.push((EPOCH_KEY.to_string(), Tensor::from(self.epoch as i64)));
+
+        // write next to the target and rename, so an interrupt never leaves half a file;
+        // an interrupt between the two renames is caught by the epoch check in restore_tensors
+        let tmp = path.with_extension("tmp");
+        Tensor::save_multi(&named, &tmp)?;
+        fs::rename(&tmp, path)?;
+
+        let json = Self::json_path(path);
+        let tmp = json.with_extension("json.tmp");
+        let mut w = BufWriter::new(fs::File::create(&tmp)?);
+        serde_json::to_writer_pretty(&mut w, self)?;
+        w.flush()?;
+        fs::rename(&tmp, &json)?;
+
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error>> {
+        let json = Self::json_path(path);
+        let file = fs::File::open(&json).map_err(|e| format!("{}: {e}", json.display()))?;
+        let state: TrainingState = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("{}: {e}", json.display()))?;
+
+        if state.format_version > TRAINING_STATE_VERSION {
+            return Err(format!("{}: unsupported training state version {}", json.display(), state.format_version).into());
+        }
+        Ok(state)
+    }
+
+    // Copies the saved weights and optimizer moments into a freshly built model and optimizer,
+    // after checking they belong to the same epoch as this state
+    pub fn restore_tensors(&self, path: impl AsRef<Path>, vs: &mut nn::VarStore, opt: &mut Optimizer) -> Result<(), Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let mut weights = HashMap::new();
+        let mut optim = HashMap::new();
+        let mut epoch = None;
+
+        for (key, t) in Tensor::load_multi_with_device(path, vs.device())? {
+            if key == EPOCH_KEY {
+                epoch = Some(t.int64_value(&[]) as usize);
+            } else if let Some(k) = key.strip_prefix("model/") {
+                weights.insert(k.to_string(), t);
+            } else if let Some(k) = key.strip_prefix("optim/") {
+                optim.insert(k.to_string(), t);
+            }
+        }
+
+        match epoch {
+            Some(epoch) if epoch != self.epoch => {
+                return Err(format!(
+                    "{}: tensors are from epoch {epoch}, {} from epoch {} (interrupted save), can't resume",
+                    path.display(),
+                    Self::json_path(path).display(),
+                    self.epoch,
+                ).into());
+            }
+            None if self.format_version >= 2 => {
+                return Err(format!("{}: no epoch stored with the tensors, can't resume", path.display()).into());
+            }
+            _ => {}
+        }
+
+        tch::no_grad(|| {
+            for (name, mut var) in vs.variables() {
+                let src = weights
+                    .get(&name)
+                    .ok_or_else(|| format!("{}: no weights for `{name}`", path.display()))?;
+                var.copy_(src);
+            }
+            Ok::<(), String>(())
+        })?;
+
+        opt.load_state(optim).map_err(|e| format!("{}: {e}", path.display()))?;
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,215 @@
+﻿use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use crate::trainer_lib::f_idx::{find_idx_file, read_idx, TEST_IMAGES, TEST_LABELS, TRAIN_IMAGES, TRAIN_LABELS};
+
+// sha256sum-style list of the decompressed files, written by `dataset import`
+const CHECKSUMS_FILE: &str = "SHA256SUMS";
+
+struct KnownFile {
+    name: &'static str,
+    // dimensions the IDX header must declare
+    dims: &'static [usize],
+    // the published MNIST archive (yann.lecun.com / cvdf mirror)
+    gz_size: u64,
+    gz_sha256: &'static str,
+    // the decompressed file
+    sha256: &'static str,
+}
+
+const MNIST_FILES: [KnownFile; 4] = [
+    KnownFile {
+        name: TRAIN_IMAGES,
+        dims: &[60_000, 28, 28],
+        gz_size: 9_912_422,
+        gz_sha256: "440fcabf73cc546fa21475e81ea370265605f56be210a4024d2ca8f203523609",
+        sha256: "ba891046e6505d7aadcbbe25680a0738ad16aec93bde7f9b65e87a2fc25776db",
+    },
+    KnownFile {
+        name: TRAIN_LABELS,
+        dims: &[60_000],
+        gz_size: 28_881,
+        gz_sha256: "3552534a0a558bbed6aed32b30c495cca23d567ec52cac8be1a0730e8010255c",
+        sha256: "65a50cbbf4e906d70832878ad85ccda5333a97f0f4c3dd2ef09a8a9eef7101c5",
+    },
+    KnownFile {
+        name: TEST_IMAGES,
+        dims: &[10_000, 28, 28],
+        gz_size: 1_648_877,
+        gz_sha256: "8d422c7b0a1c1c79245a5bcf07fe86e33eeafee792b84584aec276f5a2dbc4e6",
+        sha256: "0fa7898d509279e482958e8ce81c8e77db3f2f8254e26661ceb7762c4d494ce7",
+    },
+    KnownFile {
+        name: TEST_LABELS,
+        dims: &[10_000],
+        gz_size: 4_542,
+        gz_sha256: "f7ae60f92e00ec6debd23a6088c31dbd2371eca3ffa0defaefb259924204aec6",
+        sha256: "ff7bcfd416de33731a308c3f266cc351222c34898ecbeaf847f06e48f7ec33f2",
+    },
+];
+
+// Checks the four MNIST files in `dir`: archives and decompressed files against the published
+// hashes (and SHA256SUMS, if present), every file's IDX header and sample count
+pub fn verify_dataset(dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let dir = Path::new(dir);
+    let sums = read_checksums(dir)?;
+    let mut failed = 0;
+
+    for known in &MNIST_FILES {
+        match verify_file(dir, known, &sums) {
+            Ok(status) => println!("ok      {:<26} {status}", known.name),
+            Err(e) => {
+                println!("FAILED  {:<26} {e}", known.name);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{}: {failed} of {} files failed verification", dir.display(), MNIST_FILES.len()).into());
+    }
+    println!("{}: MNIST ok", dir.display());
+    Ok(())
+}
+
+fn verify_file(dir: &Path, known: &KnownFile, sums: &Option<HashMap<String, String>>) -> Result<String, Box<dyn std::error::Error>> {
+    let path = find_idx_file(dir, known.name)?;
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or(known.name).to_string();
+
+    let hash_status = if file_name.ends_with(".gz") {
+        check_archive(&path, known)?;
+        "archive sha256 matches".to_string()
+    } else {
+        let actual = sha256_file(&path)?;
+        check_sha256(&path, known, &actual)?;
+        if let Some(listed) = sums.as_ref().and_then(|s| s.get(&file_name))
+            && &actual != listed
+        {
+            return Err(format!("sha256 {actual} does not match {CHECKSUMS_FILE} ({listed})").into());
+        }
+        "sha256 matches".to_string()
+    };
+
+    check_structure(&path, known)?;
+    Ok(format!("{} samples, {hash_status}", known.dims[0]))
+}
+
+// Copies (and decompresses) the four MNIST files from `src` into `dir`. Archives and extracted
+// files are checked against the published hashes and the expected IDX shape before anything in
+// `dir` is replaced. Writes SHA256SUMS for later `dataset verify` runs.
+pub fn import_dataset(src: &str, dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let src = Path::new(src);
+    let dir = Path::new(dir);
+    fs::create_dir_all(dir)?;
+
+    // stage everything first, so a bad archive leaves data_dir untouched
+    let mut staged: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        for known in &MNIST_FILES {
+            let from = find_idx_file(src, known.name)?;
+            let target = dir.join(known.name);
+            let tmp = dir.join(format!("{}.import", known.name));
+            staged.push((tmp.clone(), target));
+
+            let gz = from.extension().is_some_and(|e| e == "gz");
+            if gz {
+                check_archive(&from, known)?;
+                let mut reader = GzDecoder::new(BufReader::new(fs::File::open(&from)?));
+                let mut writer = BufWriter::new(fs::File::create(&tmp)?);
+                io::copy(&mut reader, &mut writer).map_err(|e| format!("{}: {e}", from.display()))?;
+                writer.flush()?;
+            } else {
+                fs::copy(&from, &tmp).map_err(|e| format!("{}: {e}", from.display()))?;
+            }
+
+            check_sha256(&from, known, &sha256_file(&tmp)?)?;
+            check_structure(&tmp, known).map_err(|e| format!("{}: {e}", from.display()))?;
+            println!("ok      {:<26} <- {}", known.name, from.display());
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        for (tmp, _) in &staged {
+            let _ = fs::remove_file(tmp);
+        }
+        return Err(e);
+    }
+
+    let mut sums = String::new();
+    for (tmp, target) in &staged {
+        fs::rename(tmp, target)?;
+        let name = target.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        sums += &format!("{}  {name}\n", sha256_file(target)?);
+    }
+    fs::write(dir.join(CHECKSUMS_FILE), sums)?;
+
+    println!("imported MNIST into {} ({CHECKSUMS_FILE} written)", dir.display());
+    Ok(())
+}
+
+fn check_archive(path: &Path, known: &KnownFile) -> Result<(), String> {
+    let size = fs::metadata(path).map_err(|e| format!("{}: {e}", path.display()))?.len();
+    if size != known.gz_size {
+        return Err(format!("{}: {size} bytes, the MNIST archive has {}", path.display(), known.gz_size));
+    }
+    let actual = sha256_file(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    if actual != known.gz_sha256 {
+        return Err(format!("{}: sha256 {actual}, expected {}", path.display(), known.gz_sha256));
+    }
+    Ok(())
+}
+
+// The decompressed file; an empty reference hash fails instead of passing unchecked
+fn check_sha256(path: &Path, known: &KnownFile, actual: &str) -> Result<(), String> {
+    if known.sha256.is_empty() {
+        return Err(format!("{}: no reference sha256 for {}", path.display(), known.name));
+    }
+    if actual != known.sha256 {
+        return Err(format!("{}: sha256 {actual}, expected {}", path.display(), known.sha256));
+    }
+    Ok(())
+}
+
+// Header, dimensions and data size
+fn check_structure(path: &Path, known: &KnownFile) -> Result<(), String> {
+    let (dims, _) = read_idx(path).map_err(|e| e.to_string())?;
+    if dims != known.dims {
+        return Err(format!("dimensions {dims:?}, expected {:?}", known.dims));
+    }
+    Ok(())
+}
+
+fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{b:02x}")).collect())
+}
+
+// file name -> hex sha256, None when the folder has no SHA256SUMS
+fn read_checksums(dir: &Path) -> io::Result<Option<HashMap<String, String>>> {
+    let path = dir.join(CHECKSUMS_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(&path)?;
+    let sums = text
+        .lines()
+        .filter_map(|line| {
+            let (hash, name) = line.split_once(char::is_whitespace)?;
+            Some((name.trim_start_matches([' ', '*']).to_string(), hash.to_lowercase()))
+        })
+        .collect();
+    Ok(Some(sums))
+}
\ No newline at end of file
@@ -0,0 +1,43 @@
+﻿use std::path::Path;
+use tch::Device;
+use crate::shared_lib::c_trainer_config::TrainerConfig;
+use crate::shared_lib::f_ai_data::{input_shape, load_checkpoint};
+use crate::trainer_lib::f_idx::{find_idx_file, load_idx_split, TEST_IMAGES, TEST_LABELS};
+use crate::trainer_lib::c_eval_report::EvalReport;
+use crate::trainer_lib::f_mydata::load_mydata;
+
+// Evaluation report of a checkpoint on the MNIST test set in `data_dir`, or on a mydata-style folder.
+// Written next to the checkpoint as <model>_eval_test.json (MNIST) or <model>_eval_<folder>.json.
+pub fn evaluate_checkpoint(config: &TrainerConfig, model_path: &str, data_dir: &str, device: Device) -> Result<(), Box<dyn std::error::Error>> {
+    let (_vs, model, manifest) = load_checkpoint(model_path, config, device)?;
+    let dir = Path::new(data_dir);
+
+    let (x, y, tag) = if find_idx_file(dir, TEST_IMAGES).is_ok() {
+        let (images, labels) = load_idx_split(dir, TEST_IMAGES, TEST_LABELS, config.data_transposed)?;
+        if images.size()[1] != manifest.model.image_dim {
+            return Err(format!("{data_dir}: images have {} pixels, the model expects {}", images.size()[1], manifest.model.image_dim).into());
+        }
+        (images.view(input_shape(&manifest.model, -1).as_slice()), labels, "test".to_string())
+    } else {
+        let (x, y) = load_mydata(dir, &manifest.model, &manifest.preprocess, config.finetune.rerasterize);
+        let tag = dir.file_name().and_then(|s| s.to_str()).unwrap_or("data").to_string();
+        (x, y, tag)
+    };
+
+    let n = y.size()[0];
+    if n == 0 {
+        return Err(format!("no samples found in {data_dir}").into());
+    }
+    // the confusion matrix has one row per class the model knows
+    let (min, max) = (y.min().int64_value(&[]), y.max().int64_value(&[]));
+    if min < 0 || max >= manifest.model.labels {
+        let bad = if min < 0 { min } else { max };
+        return Err(format!("{data_dir}: label {bad} is outside the model's {} classes (0..{})", manifest.model.labels, manifest.model.labels - 1).into());
+    }
+
+    let report = EvalReport::compute(&model, &x, &y, &manifest.label_names, device, model_path, data_dir)?;
+    report.print();
+    println!("{model_path} on {data_dir}: acc {:5.2}% ({n} samples)", 100.0 * report.accuracy);
+    println!("report -> {}", report.save_for(model_path, &tag)?.display());
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,34 @@
+﻿use std::fs;
+use std::path::Path;
+use tch::{Device, Tensor};
+use crate::shared_lib::c_model_manifest::ModelManifest;
+use crate::shared_lib::c_trainer_config::TrainerConfig;
+use crate::shared_lib::f_ai_data::load_checkpoint;
+
+// Writes the weights of `model_path` to `out` (format picked by extension) with a manifest next to them
+pub fn export_checkpoint(config: &TrainerConfig, model_path: &str, out: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let (vs, _model, manifest) = load_checkpoint(model_path, config, Device::Cpu)?;
+    let out = Path::new(out);
+
+    if let Some(dir) = out.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    match out.extension().and_then(|e| e.to_str()) {
+        Some("npz") => {
+            let mut named: Vec<(String, Tensor)> = vs.variables().into_iter().collect();
+            named.sort_by(|a, b| a.0.cmp(&b.0));
+            Tensor::write_npz(&named, out)?;
+        }
+        // .safetensors or the libtorch format for anything else
+        _ => vs.save(out)?,
+    }
+    manifest.save_for(out)?;
+
+    println!(
+        "exported {model_path} -> {} (+ {})",
+        out.display(),
+        ModelManifest::path_for(out).display()
+    );
+    Ok(())
+}
\ No newline at end of file
@@ -0,0 +1,171 @@
+﻿use std::time::Instant;
+use tch::{nn, Device, Tensor};
+use tch::nn::ModuleT;
+use tch::vision::dataset::Dataset;
+use crate::shared_lib::c_epoch_metrics::EpochMetrics;
+use crate::shared_lib::c_finetune_config::ClassBalance;
+use crate::shared_lib::c_optimizer_settings::OptimizerSettings;
+use crate::shared_lib::c_trainer_config::TrainerConfig;
+use crate::shared_lib::f_ai_data::{input_shape, load_checkpoint};
+use crate::trainer_lib::c_augmenter::Augmenter;
+use crate::trainer_lib::c_eval_report::EvalReport;
+use crate::trainer_lib::c_lr_scheduler::LrScheduler;
+use crate::trainer_lib::c_optimizer::Optimizer;
+use crate::trainer_lib::c_run_dir::RunDir;
+use crate::trainer_lib::f_mydata::{balance_classes, class_counts, class_weights, format_counts, load_mydata};
+use crate::trainer_lib::f_training::{split_batch, split_validation, steps_per_epoch, train_epoch_with_replay, ReplaySet};
+
+// Fine-tunes the checkpoint at `base_path` and writes the result to `finetune.out_path`,
+// with a copy, the metrics and the report in `run`
+pub fn finetune_on_mydata(config: &TrainerConfig, base_path: &str, mnist: &Dataset, run: &mut RunDir) -> Result<(), Box<dyn std::error::Error>> {
+    let ft = &config.finetune;
+    let device = Device::cuda_if_available();
+    let (mut vs, model, mut manifest) = load_checkpoint(base_path, config, device)?;
+    // same seed -> same holdout split, batch order and replay samples
+    tch::manual_seed(config.seed as i64);
+    let spec = manifest.model.clone();
+
+    let (all_x, all_y) = load_mydata(&ft.data_dir, &spec, &config.preprocess, ft.rerasterize);
+    if all_y.size()[0] == 0 {
+        println!("no samples in {}, skipping fine-tune", ft.data_dir);
+        return Ok(());
+    }
+    let ((mut x, mut y), (held_x, held_y)) = split_validation(&all_x, &all_y, ft.holdout_fraction);
+    println!("mydata train: {} | held out: {}", y.size()[0], held_y.size()[0]);
+
+    // the held-out part keeps the real class mix
+    if ft.balance != ClassBalance::None || ft.max_per_class > 0 {
+        (x, y) = balance_classes(&x, &y, spec.labels, ft.balance, ft.max_per_class);
+        println!("balanced ({:?}, max {} per class): {}", ft.balance, ft.max_per_class, format_counts(&class_counts(&y, spec.labels)));
+    }
+    let weights = (ft.balance == ClassBalance::Weighted).then(|| class_weights(&y, spec.labels));
+    if let Some(w) = &weights {
+        let w = Vec::<f32>::try_from(w)?;
+        let text: Vec<String> = w.iter().enumerate().map(|(c, w)| format!("{c}: {w:.2}")).collect();
+        println!("class weights: {}", text.join(" | "));
+    }
+
+    let mnist_train_x = mnist.train_images.view(input_shape(&spec, -1).as_slice());
+    let mnist_test_x = mnist.test_images.view(input_shape(&spec, -1).as_slice());
+
+    report_scores("before fine-tune", &model, (&mnist_test_x, &mnist.test_labels), (&held_x, &held_y), device);
+
+    freeze_layers(&mut vs, &ft.frozen_layers)?;
+
+    let replay = ReplaySet {
+        images: &mnist_train_x,
+        labels: &mnist.train_labels,
+        ratio: ft.replay_ratio,
+    };
+    let (own_batch, replay_batch) = split_batch(ft.batch_size, ft.replay_ratio);
+    println!("batch: {own_batch} mydata + {replay_batch} MNIST");
+
+    let settings = OptimizerSettings { lr: ft.lr, ..config.optimizer.clone() };
+    let mut opt = Optimizer::new(&vs, &settings);
+    let mut scheduler = LrScheduler::new(&settings, steps_per_epoch(y.size()[0], own_batch), ft.epochs);
+    let augment = Augmenter::new(&config.augment, &spec);
+
+    for epoch in 1..=ft.epochs {
+        tch::manual_seed(config.seed.wrapping_add(epoch as u64) as i64);
+        let started = Instant::now();
+
+        let lr = scheduler.current_lr();
+        let loss_value = train_epoch_with_replay(&model, &mut opt, &mut scheduler, &augment, &x, &y, weights.as_ref(), &replay, ft.batch_size, device);
+
+        let acc = model.batch_accuracy_for_logits(&x, &y, device, 1024);
+        // drives a plateau schedule; the fine-tune stage still keeps its last epoch
+        let held_acc = (held_y.size()[0] > 0).then(|| model.batch_accuracy_for_logits(&held_x, &held_y, device, 1024));
+        scheduler.end_epoch(held_acc);
+        println!(
+            "ft epoch {:02} | loss {:7.4} | lr {:.2e} | acc {:5.2}%",
+            epoch,
+            loss_value,
+            lr,
+            100.0 * acc
+        );
+        run.log(&EpochMetrics {
+            stage: "finetune".to_string(),
+            epoch,
+            loss: Some(loss_value),
+            lr: Some(lr),
+            train_acc: Some(acc),
+            val_acc: held_acc,
+            test_acc: None,
+            seconds: started.elapsed().as_secs_f64(),
+        })?;
+    }
+
+    let started = Instant::now();
+    let mnist_acc = report_scores("after fine-tune", &model, (&mnist_test_x, &mnist.test_labels), (&held_x, &held_y), device);
+
+    vs.save(&ft.out_path)?;
+
+    manifest.test_accuracy = Some(mnist_acc);
+    manifest.val_accuracy = None;
+    manifest.seed = Some(config.seed);
+    manifest.run_dir = Some(run.path.display().to_string());
+    manifest.finetuned_from = Some(base_path.to_string());
+    manifest.finetune_epochs = ft.epochs;
+    manifest.preprocess = config.preprocess;
+    manifest.save_for(&ft.out_path)?;
+
+    let report = EvalReport::compute(&model, &mnist_test_x, &mnist.test_labels, &manifest.label_names, device, &ft.out_path, "MNIST test")?;
+    report.print();
+    let report_path = report.save_for(&ft.out_path, "test")?;
+    println!("report -> {}", report_path.display());
+
+    run.log(&EpochMetrics {
+        stage: "finetune_test".to_string(),
+        epoch: ft.epochs,
+        test_acc: Some(mnist_acc),
+        seconds: started.elapsed().as_secs_f64(),
+        ..Default::default()
+    })?;
+    let run_model = run.save_model(&ft.out_path)?;
+    report.save_for(&run_model, "test")?;
+    println!("saved fine-tuned weights -> {}", ft.out_path);
+    Ok(())
+}
+
+// Prints MNIST test and held-out mydata accuracy, returns the MNIST one
+fn report_scores(tag: &str, model: &impl ModuleT, mnist_test: (&Tensor, &Tensor), held_out: (&Tensor, &Tensor), device: Device) -> f64 {
+    let mnist_acc = model.batch_accuracy_for_logits(mnist_test.0, mnist_test.1, device, 1024);
+
+    let held_text = if held_out.1.size()[0] > 0 {
+        let acc = model.batch_accuracy_for_logits(held_out.0, held_out.1, device, 1024);
+        format!("{:5.2}%", 100.0 * acc)
+    } else {
+        "  -".to_string()
+    };
+
+    println!(
+        "{tag}: MNIST test acc {:5.2}% | mydata held-out acc {}",
+        100.0 * mnist_acc,
+        held_text
+    );
+    mnist_acc
+}
+
+// Stops gradient tracking for every variable under one of the `layers` prefixes.
+// A prefix without variables (a typo) or nothing left to train is an error.
+fn freeze_layers(vs: &mut nn::VarStore, layers: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let variables = vs.variables();
+    let under = |name: &str, l: &str| name == l || name.starts_with(&format!("{l}."));
+
+    if let Some(unknown) = layers.iter().find(|l| !variables.keys().any(|name| under(name, l))) {
+        let mut names: Vec<&String> = variables.keys().collect();
+        names.sort();
+        return Err(format!("finetune.frozen_layers: `{unknown}` matches no variable (model has {names:?})").into());
+    }
+    if variables.keys().all(|name| layers.iter().any(|l| under(name, l))) {
+        return Err("finetune.frozen_layers freezes every variable, nothing left to fine-tune".into());
+    }
+
+    for (name, var) in variables {
+        if layers.iter().any(|l| under(&name, l)) {
+            let _ = var.set_requires_grad(false);
+            println!("frozen: {name}");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,250 @@
+﻿use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use flate2::read::GzDecoder;
+use tch::{Kind, Tensor};
+use tch::vision::dataset::Dataset;
+
+// IDX element type: unsigned byte, the only one image / label files use
+const IDX_UBYTE: u8 = 0x08;
+
+pub const TRAIN_IMAGES: &str = "train-images-idx3-ubyte";
+pub const TRAIN_LABELS: &str = "train-labels-idx1-ubyte";
+pub const TEST_IMAGES: &str = "t10k-images-idx3-ubyte";
+pub const TEST_LABELS: &str = "t10k-labels-idx1-ubyte";
+
+fn invalid(path: &Path, msg: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{}: {msg}", path.display()))
+}
+
+// Other names datasets use for the same file: EMNIST calls the t10k split "test"
+fn idx_aliases(name: &str) -> Vec<String> {
+    let mut names = vec![name.to_string()];
+    if let Some(rest) = name.strip_prefix("t10k-") {
+        names.push(format!("test-{rest}"));
+    }
+    names
+}
+
+// Finds `name` in `dir`, also as `name.gz`, with a prefix or under an alias, e.g. Fashion-MNIST's
+// train-images-idx3-ubyte.gz or EMNIST's emnist-digits-test-images-idx3-ubyte
+pub fn find_idx_file(dir: impl AsRef<Path>, name: &str) -> io::Result<PathBuf> {
+    let dir = dir.as_ref();
+    let rd = fs::read_dir(dir).map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", dir.display())))?;
+    let names = idx_aliases(name);
+
+    let mut found: Vec<PathBuf> = rd
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| {
+            let file = p.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            let file = file.strip_suffix(".gz").unwrap_or(file);
+            names.iter().any(|n| file.ends_with(n.as_str())) && p.is_file()
+        })
+        .collect();
+    found.sort();
+
+    match found.len() {
+        0 => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{}: missing {name} (or {name}.gz)", dir.display()),
+        )),
+        1 => Ok(found.remove(0)),
+        _ => {
+            // the exact name wins over prefixed variants
+            if let Some(exact) = found.iter().find(|p| p.file_name().is_some_and(|f| names.iter().any(|n| f == n.as_str()))) {
+                return Ok(exact.clone());
+            }
+            let names: Vec<String> = found.iter().map(|p| p.display().to_string()).collect();
+            Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{}: several files match {name}: {}", dir.display(), names.join(", ")),
+            ))
+        }
+    }
+}
+
+// Reads an unsigned-byte IDX file (plain or .gz), returns (dims, data)
+pub fn read_idx(path: impl AsRef<Path>) -> io::Result<(Vec<usize>, Vec<u8>)> {
+    let path = path.as_ref();
+    let file = fs::File::open(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+    let mut reader: Box<dyn Read> = if path.extension().is_some_and(|e| e == "gz") {
+        Box::new(GzDecoder::new(BufReader::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic).map_err(|e| invalid(path, format!("can't read header: {e}")))?;
+    if magic[0] != 0 || magic[1] != 0 {
+        return Err(invalid(path, format!("not an IDX file (magic {:02x}{:02x}{:02x}{:02x})", magic[0], magic[1], magic[2], magic[3])));
+    }
+    if magic[2] != IDX_UBYTE {
+        return Err(invalid(path, format!("unsupported IDX element type 0x{:02x}, expected unsigned bytes", magic[2])));
+    }
+    let ndim = magic[3] as usize;
+    if ndim == 0 || ndim > 4 {
+        return Err(invalid(path, format!("unsupported number of dimensions {ndim}")));
+    }
+
+    let mut dims = Vec::with_capacity(ndim);
+    for _ in 0..ndim {
+        let mut d = [0u8; 4];
+        reader.read_exact(&mut d).map_err(|e| invalid(path, format!("can't read dimensions: {e}")))?;
+        dims.push(u32::from_be_bytes(d) as usize);
+    }
+
+    // the header is not trusted: no overflow, and plain files must have exactly that much data
+    let expected = dims
+        .iter()
+        .try_fold(1usize, |n, &d| n.checked_mul(d))
+        .ok_or_else(|| invalid(path, format!("dimensions {dims:?} are too large")))?;
+    if path.extension().is_none_or(|e| e != "gz") {
+        let available = fs::metadata(path)?.len().saturating_sub(4 + 4 * ndim as u64);
+        if available != expected as u64 {
+            return Err(invalid(path, format!("dimensions {dims:?} need {expected} bytes of data, file has {available}")));
+        }
+    }
+
+    // at most one byte more than needed, so trailing data is noticed without reading all of it
+    let mut data = Vec::new();
+    reader.take(expected as u64 + 1).read_to_end(&mut data).map_err(|e| invalid(path, e))?;
+    if data.len() != expected {
+        let found = if data.len() > expected { "more".to_string() } else { data.len().to_string() };
+        return Err(invalid(path, format!("dimensions {dims:?} need {expected} bytes of data, file has {found}")));
+    }
+
+    Ok((dims, data))
+}
+
+// Writes an unsigned-byte IDX file (uncompressed, what mnist::load_dir expects)
+pub fn write_idx(path: impl AsRef<Path>, dims: &[usize], data: &[u8]) -> io::Result<()> {
+    let path = path.as_ref();
+    if dims.iter().product::<usize>() != data.len() {
+        return Err(invalid(path, format!("dimensions {dims:?} don't match {} bytes of data", data.len())));
+    }
+
+    let file = fs::File::create(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+    let mut w = BufWriter::new(file);
+    w.write_all(&[0, 0, IDX_UBYTE, dims.len() as u8])?;
+    for &d in dims {
+        w.write_all(&(d as u32).to_be_bytes())?;
+    }
+    w.write_all(data)?;
+    w.flush()
+}
+
+// One split ("train" or "t10k" file names): images as [n, rows * cols] floats in 0..1, labels as i64.
+// `transposed` flips every image, EMNIST stores them column by column.
+pub fn load_idx_split(dir: impl AsRef<Path>, images_name: &str, labels_name: &str, transposed: bool) -> io::Result<(Tensor, Tensor)> {
+    let dir = dir.as_ref();
+    let images_path = find_idx_file(dir, images_name)?;
+    let labels_path = find_idx_file(dir, labels_name)?;
+
+    let (dims, pixels) = read_idx(&images_path)?;
+    if dims.len() != 3 {
+        return Err(invalid(&images_path, format!("expected 3 dimensions (count, rows, cols), found {dims:?}")));
+    }
+    let (lab_dims, labels) = read_idx(&labels_path)?;
+    if lab_dims.len() != 1 {
+        return Err(invalid(&labels_path, format!("expected 1 dimension (count), found {lab_dims:?}")));
+    }
+    if dims[0] != lab_dims[0] {
+        return Err(invalid(&labels_path, format!("{} labels for {} images in {}", lab_dims[0], dims[0], images_path.display())));
+    }
+
+    if dims[0] == 0 {
+        return Err(invalid(&images_path, "contains no images"));
+    }
+
+    let (n, rows, cols) = (dims[0] as i64, dims[1] as i64, dims[2] as i64);
+    let mut images = Tensor::from_slice(&pixels).view([n, rows, cols]);
+    if transposed {
+        images = images.transpose(1, 2).contiguous();
+    }
+    let images = images.view([n, rows * cols]).to_kind(Kind::Float) / 255.0;
+    let labels = Tensor::from_slice(&labels).to_kind(Kind::Int64);
+
+    Ok((images, labels))
+}
+
+// MNIST-style dataset folder: the four IDX files, plain or gzipped, optionally prefixed
+pub fn load_idx_dataset(dir: impl AsRef<Path>, transposed: bool) -> io::Result<Dataset> {
+    let dir = dir.as_ref();
+    let (train_images, train_labels) = load_idx_split(dir, TRAIN_IMAGES, TRAIN_LABELS, transposed)?;
+    let (test_images, test_labels) = load_idx_split(dir, TEST_IMAGES, TEST_LABELS, transposed)?;
+
+    if train_images.size()[1] != test_images.size()[1] {
+        return Err(invalid(dir, format!(
+            "train images have {} pixels, test images {}",
+            train_images.size()[1],
+            test_images.size()[1]
+        )));
+    }
+    let labels = train_labels.max().int64_value(&[]).max(test_labels.max().int64_value(&[])) + 1;
+
+    Ok(Dataset {
+        train_images,
+        train_labels,
+        test_images,
+        test_labels,
+        labels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("f_idx_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn truncated_file_is_rejected() {
+        let dir = temp_dir("truncated");
+        let path = dir.join(TRAIN_LABELS);
+        write_idx(&path, &[4], &[1, 2, 3, 4]).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 1]).unwrap();
+
+        let err = read_idx(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn bad_magic_is_rejected() {
+        let dir = temp_dir("magic");
+        let path = dir.join(TRAIN_LABELS);
+        fs::write(&path, [0x1f, 0x8b, 0x08, 0x01, 0, 0, 0, 1, 7]).unwrap();
+
+        let err = read_idx(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn gz_round_trip() {
+        let dir = temp_dir("gz");
+        let plain = dir.join("plain");
+        let data: Vec<u8> = (0..2 * 3 * 4).map(|i| i as u8).collect();
+        write_idx(&plain, &[2, 3, 4], &data).unwrap();
+
+        let gz = dir.join(format!("emnist-digits-{}.gz", TEST_IMAGES.replace("t10k-", "test-")));
+        let mut enc = GzEncoder::new(fs::File::create(&gz).unwrap(), Compression::default());
+        enc.write_all(&fs::read(&plain).unwrap()).unwrap();
+        enc.finish().unwrap();
+
+        assert_eq!(find_idx_file(&dir, TEST_IMAGES).unwrap(), gz);
+        let (dims, read) = read_idx(&gz).unwrap();
+        assert_eq!(dims, vec![2, 3, 4]);
+        assert_eq!(read, data);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,140 @@
+﻿use std::path::Path;
+use tch::{Device, Kind, Tensor};
+use crate::shared_lib::c_finetune_config::ClassBalance;
+use crate::shared_lib::c_model_manifest::ModelSpec;
+use crate::shared_lib::c_preprocess_config::PreprocessConfig;
+use crate::shared_lib::c_sample::load_samples;
+use crate::shared_lib::f_ai_data::input_shape;
+use crate::shared_lib::f_rasterize::rasterize_to_mnist28;
+
+// `rerasterize` rebuilds every sample that has a stroke recording with `preprocess`
+// instead of using the pixels saved back then
+pub fn load_mydata(dir: impl AsRef<Path>, spec: &ModelSpec, preprocess: &PreprocessConfig, rerasterize: bool) -> (Tensor, Tensor) {
+    let dir = dir.as_ref();
+    let (samples, problems) = load_samples(dir);
+    for p in &problems {
+        println!("mydata: {p}");
+    }
+
+    let mut images: Vec<f32> = Vec::new();
+    let mut labels: Vec<i64> = Vec::new();
+    let mut legacy = 0;
+    let mut redrawn = 0;
+    let mut other_pipeline = 0;
+
+    for s in &samples {
+        if s.meta.is_none() {
+            legacy += 1;
+        }
+
+        let recording = s.meta.as_ref().and_then(|m| m.recording()).filter(|_| rerasterize);
+        let pixels: Vec<f32> = match recording {
+            Some((canvas, strokes)) => {
+                redrawn += 1;
+                rasterize_to_mnist28(canvas, strokes, preprocess)
+            }
+            None => {
+                if s.meta.as_ref().is_some_and(|m| m.preprocess != *preprocess) {
+                    other_pipeline += 1;
+                }
+                s.pixels01()
+            }
+        };
+
+        if pixels.len() as i64 != spec.image_dim {
+            println!("mydata: {}: {} pixels, the model takes {}, skipped", s.path.display(), pixels.len(), spec.image_dim);
+            continue;
+        }
+        images.extend(pixels);
+        labels.push(s.label as i64);
+    }
+
+    let n = labels.len() as i64;
+    println!("Loaded mydata samples: {n} ({legacy} legacy .bin without metadata)");
+    if rerasterize {
+        println!("re-rasterized {redrawn} stroke recordings, {} samples use their saved pixels", n - redrawn);
+    }
+    if other_pipeline > 0 {
+        println!("{other_pipeline} samples were saved with a different preprocessing than the model's, see finetune.rerasterize");
+    }
+
+    let x = Tensor::from_slice(&images)
+        .to_kind(Kind::Float)
+        .view(input_shape(spec, n).as_slice());
+
+    let y = Tensor::from_slice(&labels).to_kind(Kind::Int64);
+    println!("per class: {}", format_counts(&class_counts(&y, spec.labels)));
+
+    (x, y)
+}
+
+pub fn class_counts(labels: &Tensor, classes: i64) -> Vec<i64> {
+    let mut counts = vec![0i64; classes as usize];
+    for l in Vec::<i64>::try_from(labels.to_kind(Kind::Int64)).expect("labels to vec") {
+        if let Some(c) = counts.get_mut(l as usize) {
+            *c += 1;
+        }
+    }
+    counts
+}
+
+// "0: 12 | 1: 30 | ..." plus a warning when the largest class has 5x the samples of the smallest
+pub fn format_counts(counts: &[i64]) -> String {
+    let text: Vec<String> = counts.iter().enumerate().map(|(c, n)| format!("{c}: {n}")).collect();
+    let mut text = text.join(" | ");
+
+    let present: Vec<i64> = counts.iter().copied().filter(|&n| n > 0).collect();
+    let (min, max) = (present.iter().min().copied().unwrap_or(0), present.iter().max().copied().unwrap_or(0));
+    if min > 0 && max >= 5 * min {
+        text += &format!("  (unbalanced {max}:{min}, see finetune.balance)");
+    }
+    text
+}
+
+// Caps every class at `max_per_class` (0 = no cap), then oversamples smaller classes for
+// ClassBalance::Oversample. Random picks come from the libtorch RNG, so they follow the seed.
+pub fn balance_classes(images: &Tensor, labels: &Tensor, classes: i64, balance: ClassBalance, max_per_class: usize) -> (Tensor, Tensor) {
+    let all = Vec::<i64>::try_from(labels.to_kind(Kind::Int64)).expect("labels to vec");
+    let per_class: Vec<Vec<i64>> = (0..classes)
+        .map(|c| all.iter().enumerate().filter(|&(_, &l)| l == c).map(|(i, _)| i as i64).collect())
+        .collect();
+
+    let mut picked: Vec<Vec<i64>> = per_class
+        .into_iter()
+        .map(|idx| {
+            if max_per_class == 0 || idx.len() <= max_per_class {
+                return idx;
+            }
+            let perm = Vec::<i64>::try_from(Tensor::randperm(idx.len() as i64, (Kind::Int64, Device::Cpu))).expect("randperm");
+            perm[..max_per_class].iter().map(|&k| idx[k as usize]).collect()
+        })
+        .collect();
+
+    if balance == ClassBalance::Oversample {
+        let target = picked.iter().map(|idx| idx.len()).max().unwrap_or(0);
+        for idx in picked.iter_mut().filter(|idx| !idx.is_empty()) {
+            let missing = (target - idx.len()) as i64;
+            if missing > 0 {
+                let extra = Tensor::randint(idx.len() as i64, [missing], (Kind::Int64, Device::Cpu));
+                let extra: Vec<i64> = Vec::<i64>::try_from(extra).expect("randint").iter().map(|&k| idx[k as usize]).collect();
+                idx.extend(extra);
+            }
+        }
+    }
+
+    let idx: Vec<i64> = picked.into_iter().flatten().collect();
+    let idx = Tensor::from_slice(&idx);
+    (images.index_select(0, &idx), labels.index_select(0, &idx))
+}
+
+// n / (classes present * n_c) per class, so the weights average to 1 over the samples; 0 for empty classes
+pub fn class_weights(labels: &Tensor, classes: i64) -> Tensor {
+    let counts = class_counts(labels, classes);
+    let n: i64 = counts.iter().sum();
+    let present = counts.iter().filter(|&&c| c > 0).count() as f64;
+    let weights: Vec<f32> = counts
+        .iter()
+        .map(|&c| if c > 0 { (n as f64 / (present * c as f64)) as f32 } else { 0.0 })
+        .collect();
+    Tensor::from_slice(&weights)
+}
\ No newline at end of file
@@ -0,0 +1,126 @@
+﻿use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tch::{Device, Kind, Tensor};
+use crate::shared_lib::c_preprocess_config::PreprocessConfig;
+use crate::shared_lib::c_sample::{load_samples, Sample, SampleMeta, SAMPLE_SIDE, SAMPLE_VERSION};
+use crate::trainer_lib::f_idx::{find_idx_file, read_idx, write_idx, TEST_IMAGES, TEST_LABELS, TRAIN_IMAGES, TRAIN_LABELS};
+
+// Packs mydata/<label>/*.bin into MNIST-named IDX files in `out`.
+// test_fraction > 0 moves that share of every class into the t10k files, the split depends on `seed` only.
+pub fn export_mydata(src: impl AsRef<Path>, out: impl AsRef<Path>, test_fraction: f64, seed: u64) -> io::Result<()> {
+    let (src, out) = (src.as_ref(), out.as_ref());
+    let (samples, problems) = load_samples(src);
+    for p in &problems {
+        eprintln!("skipped {p}");
+    }
+
+    let side = SAMPLE_SIDE as usize;
+    let (samples, other_size): (Vec<Sample>, Vec<Sample>) = samples.into_iter().partition(|s| s.pixels.len() == side * side);
+    for s in &other_size {
+        eprintln!("skipped {}: not {side}x{side}", s.path.display());
+    }
+    if samples.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("{}: no samples to export", src.display())));
+    }
+
+    // stratified: every class keeps its share in both splits
+    tch::manual_seed(seed as i64);
+    let mut train: Vec<&Sample> = Vec::new();
+    let mut test: Vec<&Sample> = Vec::new();
+    for label in 0..10u8 {
+        let class: Vec<&Sample> = samples.iter().filter(|s| s.label == label).collect();
+        let n = class.len();
+        if n == 0 {
+            continue;
+        }
+        let n_test = ((n as f64) * test_fraction.clamp(0.0, 1.0)).round() as usize;
+        let perm = Vec::<i64>::try_from(Tensor::randperm(n as i64, (Kind::Int64, Device::Cpu))).map_err(io::Error::other)?;
+        for (k, &i) in perm.iter().enumerate() {
+            if k < n_test { test.push(class[i as usize]) } else { train.push(class[i as usize]) }
+        }
+    }
+
+    fs::create_dir_all(out)?;
+    write_split(out, TRAIN_IMAGES, TRAIN_LABELS, &train)?;
+    if !test.is_empty() {
+        write_split(out, TEST_IMAGES, TEST_LABELS, &test)?;
+    }
+    println!("{}: {} train / {} test samples written to {}", src.display(), train.len(), test.len(), out.display());
+    if test.is_empty() {
+        println!("no t10k files written, use --test-fraction to get a folder mnist::load_dir can read");
+    }
+    Ok(())
+}
+
+fn write_split(out: &Path, images_name: &str, labels_name: &str, samples: &[&Sample]) -> io::Result<()> {
+    let side = SAMPLE_SIDE as usize;
+    let pixels: Vec<u8> = samples.iter().flat_map(|s| s.pixels.iter().copied()).collect();
+    let labels: Vec<u8> = samples.iter().map(|s| s.label).collect();
+    write_idx(out.join(images_name), &[samples.len(), side, side], &pixels)?;
+    write_idx(out.join(labels_name), &[samples.len()], &labels)
+}
+
+// Unpacks the IDX files in `src` (train, and t10k when present) into mydata/<label>/ samples
+pub fn import_mydata(src: impl AsRef<Path>, dir: impl AsRef<Path>) -> io::Result<()> {
+    let (src, dir) = (src.as_ref(), dir.as_ref());
+    let mut splits = vec![(TRAIN_IMAGES, TRAIN_LABELS)];
+    if find_idx_file(src, TEST_IMAGES).is_ok() {
+        splits.push((TEST_IMAGES, TEST_LABELS));
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+    let mut imported = 0u64;
+    for (images_name, labels_name) in splits {
+        let images_path = find_idx_file(src, images_name)?;
+        let labels_path = find_idx_file(src, labels_name)?;
+        let (dims, pixels) = read_idx(&images_path)?;
+        let (lab_dims, labels) = read_idx(&labels_path)?;
+
+        let side = SAMPLE_SIDE as usize;
+        if dims.len() != 3 || dims[1] != side || dims[2] != side {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: expected [count, {side}, {side}] images, found {dims:?}", images_path.display()),
+            ));
+        }
+        if lab_dims != [dims[0]] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {lab_dims:?} labels for {} images", labels_path.display(), dims[0]),
+            ));
+        }
+        if let Some(bad) = labels.iter().find(|&&l| l > 9) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: label {bad}, mydata only holds digits 0..9", labels_path.display()),
+            ));
+        }
+
+        for (image, &label) in pixels.chunks_exact(side * side).zip(&labels) {
+            let meta = SampleMeta {
+                format_version: SAMPLE_VERSION,
+                label,
+                width: SAMPLE_SIDE,
+                height: SAMPLE_SIDE,
+                // distinct file names without probing for a free one each time
+                created_ms: now + imported,
+                brush_size: 0.0,
+                strokes: vec![],
+                canvas: None,
+                preprocess: PreprocessConfig::default(),
+                prediction: None,
+                probs: vec![],
+                model: String::new(),
+                author: format!("import:{}", images_path.display()),
+            };
+            Sample::save(dir, image, &meta)?;
+            imported += 1;
+        }
+        println!("{}: {} samples", images_path.display(), dims[0]);
+    }
+
+    println!("{imported} samples imported into {}", dir.display());
+    Ok(())
+}
\ No newline at end of file
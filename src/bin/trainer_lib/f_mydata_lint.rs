@@ -0,0 +1,173 @@
+﻿use std::collections::HashMap;
+use tch::{Device, Kind, Tensor};
+use crate::shared_lib::c_sample::{load_samples, Sample};
+use crate::shared_lib::c_trainer_config::TrainerConfig;
+use crate::shared_lib::f_ai_data::{input_shape, load_checkpoint};
+use crate::trainer_lib::c_eval_report::predict_probs;
+
+// a pixel above this counts as ink
+const INK_LEVEL: u8 = 32;
+// fewer ink pixels than this is a blank canvas (a thin "1" has ~40)
+const MIN_INK_PIXELS: usize = 12;
+// mean absolute pixel difference (0..255) below which two samples are near-duplicates
+const NEAR_DUPLICATE_DIST: f64 = 3.0;
+// the model has to be at least this sure of another class
+const DISAGREE_PROB: f32 = 0.9;
+
+struct Finding {
+    sample: usize,
+    kind: &'static str,
+    detail: String,
+}
+
+// Reports duplicates, near-duplicates, blank images and samples the model is sure are mislabeled.
+// With `quarantine` the flagged samples are moved to <quarantine>/<label>/, out of reach of load_mydata.
+pub fn lint_mydata(
+    config: &TrainerConfig,
+    data_dir: &str,
+    model_path: &str,
+    quarantine: Option<&str>,
+    device: Device,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (mut samples, problems) = load_samples(data_dir);
+    for p in &problems {
+        println!("unreadable  {p}");
+    }
+    if samples.is_empty() {
+        return Err(format!("no samples found in {data_dir}").into());
+    }
+
+    // oldest first, so the original of a duplicate is the one that stays
+    samples.sort_by_key(|s| (s.meta.as_ref().map(|m| m.created_ms).unwrap_or(0), s.path.clone()));
+
+    let mut findings = Vec::new();
+    let ink: Vec<usize> = samples.iter().map(|s| s.pixels.iter().filter(|&&p| p > INK_LEVEL).count()).collect();
+    for (i, &n) in ink.iter().enumerate() {
+        if n < MIN_INK_PIXELS {
+            findings.push(Finding{ sample: i, kind: "blank", detail: format!("{n} ink pixels") });
+        }
+    }
+    let blank = |i: usize| ink[i] < MIN_INK_PIXELS;
+
+    find_duplicates(&samples, &blank, &mut findings);
+
+    match load_checkpoint(model_path, config, device) {
+        Ok((_vs, model, manifest)) => {
+            let usable: Vec<usize> = (0..samples.len())
+                .filter(|&i| samples[i].pixels.len() as i64 == manifest.model.image_dim && !blank(i))
+                .collect();
+            if usable.is_empty() {
+                println!("no model check: no sample has the model's image size");
+            } else {
+                // same input the model was trained on
+                let pixels: Vec<f32> = usable
+                    .iter()
+                    .flat_map(|&i| samples[i].pixels01())
+                    .map(|p| manifest.normalize(p))
+                    .collect();
+                let x = Tensor::from_slice(&pixels).view(input_shape(&manifest.model, usable.len() as i64).as_slice());
+                let (probs, classes) = predict_probs(&model, &x, device, 1024).max_dim(-1, false);
+                let probs = Vec::<f32>::try_from(probs.to_kind(Kind::Float))?;
+                let classes = Vec::<i64>::try_from(classes)?;
+
+                for (k, &i) in usable.iter().enumerate() {
+                    if classes[k] != samples[i].label as i64 && probs[k] >= DISAGREE_PROB {
+                        let detail = format!("label {}, model says {} ({:.1}%)", samples[i].label, classes[k], 100.0 * probs[k]);
+                        findings.push(Finding{ sample: i, kind: "disagrees", detail });
+                    }
+                }
+            }
+        }
+        Err(e) => println!("no model check: {e}"),
+    }
+
+    findings.sort_by_key(|f| f.sample);
+    for f in &findings {
+        println!("{:<11} {}  {}", f.kind, samples[f.sample].path.display(), f.detail);
+    }
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for f in &findings {
+        *counts.entry(f.kind).or_default() += 1;
+    }
+    let mut flagged: Vec<usize> = findings.iter().map(|f| f.sample).collect();
+    flagged.dedup();
+    println!(
+        "{}: {} samples, {} flagged (duplicate {}, near-duplicate {}, blank {}, disagrees {})",
+        data_dir,
+        samples.len(),
+        flagged.len(),
+        counts.get("duplicate").unwrap_or(&0),
+        counts.get("near-dup").unwrap_or(&0),
+        counts.get("blank").unwrap_or(&0),
+        counts.get("disagrees").unwrap_or(&0),
+    );
+
+    match quarantine {
+        Some(dir) => {
+            for &i in &flagged {
+                let label = samples[i].label;
+                samples[i].move_to(dir, label)?;
+            }
+            println!("{} samples moved to {dir}", flagged.len());
+        }
+        None if !flagged.is_empty() => println!("use --quarantine <dir> to move them out of {data_dir}"),
+        None => {}
+    }
+    Ok(())
+}
+
+// Exact copies by pixel content, near copies by mean absolute difference; blank samples are skipped
+fn find_duplicates(samples: &[Sample], blank: &dyn Fn(usize) -> bool, findings: &mut Vec<Finding>) {
+    let mut exact = vec![false; samples.len()];
+    let mut seen: HashMap<&[u8], usize> = HashMap::new();
+    for (i, s) in samples.iter().enumerate() {
+        if blank(i) {
+            continue;
+        }
+        if let Some(&first) = seen.get(s.pixels.as_slice()) {
+            exact[i] = true;
+            let detail = format!("same pixels as {} (label {})", samples[first].path.display(), samples[first].label);
+            findings.push(Finding{ sample: i, kind: "duplicate", detail });
+        } else {
+            seen.insert(&s.pixels, i);
+        }
+    }
+
+    // mean |a - b| >= |sum(a) - sum(b)| / n, so after sorting by ink sum only neighbours can be close
+    let sums: Vec<u64> = samples.iter().map(|s| s.pixels.iter().map(|&p| p as u64).sum()).collect();
+    let mut order: Vec<usize> = (0..samples.len()).filter(|&i| !blank(i) && !exact[i]).collect();
+    order.sort_by_key(|&i| sums[i]);
+
+    let mut near = vec![false; samples.len()];
+    for (a, &i) in order.iter().enumerate() {
+        if near[i] {
+            continue;
+        }
+        let n = samples[i].pixels.len() as f64;
+        for &j in &order[a + 1..] {
+            if (sums[j] - sums[i]) as f64 / n >= NEAR_DUPLICATE_DIST {
+                break;
+            }
+            if near[j] || samples[j].pixels.len() != samples[i].pixels.len() {
+                continue;
+            }
+            let diff: u64 = samples[i].pixels.iter().zip(&samples[j].pixels).map(|(&a, &b)| a.abs_diff(b) as u64).sum();
+            let dist = diff as f64 / n;
+            if dist < NEAR_DUPLICATE_DIST {
+                // flag the newer one of the pair
+                let (keep, dup) = if i < j { (i, j) } else { (j, i) };
+                near[dup] = true;
+                let detail = format!(
+                    "mean difference {dist:.1} to {} (label {})",
+                    samples[keep].path.display(),
+                    samples[keep].label
+                );
+                findings.push(Finding{ sample: dup, kind: "near-dup", detail });
+                if dup == i {
+                    break;
+                }
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,176 @@
+﻿use std::time::Instant;
+use tch::{nn, Device};
+use tch::nn::ModuleT;
+use tch::vision::dataset::Dataset;
+use crate::shared_lib::c_epoch_metrics::EpochMetrics;
+use crate::shared_lib::c_model_manifest::{ModelManifest, ModelSpec};
+use crate::shared_lib::c_trainer_config::TrainerConfig;
+use crate::shared_lib::f_ai_data::{build_model, input_shape};
+use crate::trainer_lib::c_augmenter::Augmenter;
+use crate::trainer_lib::c_early_stopping::EarlyStopping;
+use crate::trainer_lib::c_eval_report::EvalReport;
+use crate::trainer_lib::c_lr_scheduler::LrScheduler;
+use crate::trainer_lib::c_optimizer::Optimizer;
+use crate::trainer_lib::c_run_dir::RunDir;
+use crate::trainer_lib::c_training_state::{TrainingState, TRAINING_STATE_VERSION};
+use crate::trainer_lib::f_training::{split_validation, steps_per_epoch, train_epoch};
+
+// Trains the base model from scratch (or from a `resume` checkpoint) and saves the
+// best-by-validation weights to `out_path`, with a copy, the metrics and the report in `run`
+pub fn train_on_mnist(config: &TrainerConfig, m: &Dataset, device: Device, resume: Option<&str>, run: &mut RunDir) -> Result<(), Box<dyn std::error::Error>> {
+    let spec = ModelSpec::from_config(config);
+
+    let resumed = match resume {
+        Some(path) => Some(TrainingState::load(path)?),
+        None => None,
+    };
+    if let Some(state) = resumed.as_ref().filter(|state| state.model != spec) {
+        return Err(format!("checkpoint was trained with {:?}, config.json describes {:?}", state.model, spec).into());
+    }
+
+    // the split, the initial weights and every epoch's shuffle derive from this seed,
+    // so a resumed run sees exactly the same data order
+    let seed = match &resumed {
+        Some(state) => {
+            if state.seed != config.seed {
+                println!("note: continuing with the checkpoint's seed {} (config seed {})", state.seed, config.seed);
+            }
+            state.seed
+        }
+        None => config.seed,
+    };
+    tch::manual_seed(seed as i64);
+
+    // batches are moved to the device by the iterator, the full sets stay on the CPU
+    let all_train_images = m.train_images.view(input_shape(&spec, -1).as_slice());
+    let ((train_images, train_labels), (val_images, val_labels)) =
+        split_validation(&all_train_images, &m.train_labels, config.val_fraction);
+    let test_images = m.test_images.view(input_shape(&spec, -1).as_slice());
+    let test_labels = m.test_labels.shallow_clone();
+    println!("train: {} | val: {} | test: {}", train_labels.size()[0], val_labels.size()[0], test_labels.size()[0]);
+
+    let mut vs = nn::VarStore::new(device);
+    let root = &vs.root();
+    let model = build_model(root, &spec);
+
+
+    let mut opt = Optimizer::new(&vs, &config.optimizer);
+    let mut scheduler = LrScheduler::new(
+        &config.optimizer,
+        steps_per_epoch(train_labels.size()[0], config.batch_size),
+        config.epoch,
+    );
+    let mut stopping = EarlyStopping::new(config.patience);
+    let augment = Augmenter::new(&config.augment, &spec);
+    let has_val = val_labels.size()[0] > 0;
+
+    // written with every weights save, so out_path never holds weights next to another model's manifest
+    let mut manifest = ModelManifest::from_config(config);
+    manifest.seed = Some(seed);
+    manifest.run_dir = Some(run.path.display().to_string());
+
+    let mut first_epoch = 1;
+    if let (Some(path), Some(state)) = (resume, resumed) {
+        state.restore_tensors(path, &mut vs, &mut opt)?;
+        scheduler.restore(state.scheduler);
+        stopping = state.stopping;
+        first_epoch = state.epoch + 1;
+        println!("resumed {path} after epoch {} (seed {seed})", state.epoch);
+    }
+
+    for epoch in first_epoch..= config.epoch {
+        tch::manual_seed(seed.wrapping_add(epoch as u64) as i64);
+        let started = Instant::now();
+
+        let lr = scheduler.current_lr();
+        let loss_value = train_epoch(&model, &mut opt, &mut scheduler, &augment, &train_images, &train_labels, None, config.batch_size, device);
+
+        let train_acc = model.batch_accuracy_for_logits(&train_images, &train_labels, device, 1024);
+        let val_acc = has_val.then(|| model.batch_accuracy_for_logits(&val_images, &val_labels, device, 1024));
+        scheduler.end_epoch(val_acc);
+
+        // without a validation split the latest weights are always kept
+        let improved = stopping.update(epoch, val_acc.unwrap_or(epoch as f64));
+        if improved {
+            manifest.epochs = epoch;
+            manifest.val_accuracy = val_acc;
+            vs.save(&config.out_path)?;
+            manifest.save_for(&config.out_path)?;
+        }
+
+        let val_text = match val_acc {
+            Some(acc) => format!("{:5.2}%", 100.0 * acc),
+            None => "  -   ".to_string(),
+        };
+        println!(
+            "epoch {:3}/{:3} | loss {:8.5} | lr {:.2e} | train acc {:5.2}% | val acc {}{}",
+            epoch,
+            config.epoch,
+            loss_value,
+            lr,
+            100.0 * train_acc,
+            val_text,
+            if improved { " *" } else { "" }
+        );
+        run.log(&EpochMetrics {
+            stage: "train".to_string(),
+            epoch,
+            loss: Some(loss_value),
+            lr: Some(lr),
+            train_acc: Some(train_acc),
+            val_acc,
+            test_acc: None,
+            seconds: started.elapsed().as_secs_f64(),
+        })?;
+
+        if config.checkpoint_every > 0 && epoch % config.checkpoint_every == 0 {
+            let state = TrainingState {
+                format_version: TRAINING_STATE_VERSION,
+                epoch,
+                seed,
+                model: spec.clone(),
+                stopping: stopping.clone(),
+                scheduler: scheduler.progress().clone(),
+            };
+            state.save(&config.checkpoint_path, &vs, &opt)?;
+        }
+
+        if stopping.should_stop() {
+            println!("early stop: no val improvement for {} epochs", stopping.stale_epochs);
+            break;
+        }
+    }
+
+    // test set is only looked at once, with the selected weights
+    vs.load(&config.out_path)?;
+    let started = Instant::now();
+    let test_acc = model.batch_accuracy_for_logits(&test_images, &test_labels, device, 1024);
+    println!(
+        "best epoch {} | test acc {:5.2}%",
+        stopping.best_epoch,
+        100.0 * test_acc
+    );
+
+    let report = EvalReport::compute(&model, &test_images, &test_labels, &manifest.label_names, device, &config.out_path, "MNIST test")?;
+    report.print();
+    println!("report -> {}", report.save_for(&config.out_path, "test")?.display());
+
+    manifest.epochs = stopping.best_epoch;
+    manifest.val_accuracy = if has_val { stopping.best } else { None };
+    manifest.test_accuracy = Some(test_acc);
+    manifest.save_for(&config.out_path)?;
+    println!("saved {:?} weights -> {}", config.architecture, config.out_path);
+
+    run.log(&EpochMetrics {
+        stage: "test".to_string(),
+        epoch: stopping.best_epoch,
+        val_acc: manifest.val_accuracy,
+        test_acc: Some(test_acc),
+        seconds: started.elapsed().as_secs_f64(),
+        ..Default::default()
+    })?;
+    let run_model = run.save_model(&config.out_path)?;
+    report.save_for(&run_model, "test")?;
+
+    Ok(())
+}
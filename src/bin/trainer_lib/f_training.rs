@@ -0,0 +1,138 @@
+﻿use tch::{Device, Kind, Tensor};
+use tch::data::Iter2;
+use tch::nn::Module;
+use crate::trainer_lib::c_augmenter::Augmenter;
+use crate::trainer_lib::c_lr_scheduler::LrScheduler;
+use crate::trainer_lib::c_optimizer::Optimizer;
+
+// One pass over the whole set in shuffled, augmented mini-batches, returns the sample-averaged loss
+#[allow(clippy::too_many_arguments)]
+pub fn train_epoch(
+    model: &impl Module,
+    opt: &mut Optimizer,
+    scheduler: &mut LrScheduler,
+    augment: &Augmenter,
+    images: &Tensor,
+    labels: &Tensor,
+    class_weights: Option<&Tensor>,
+    batch_size: i64,
+    device: Device,
+) -> f64 {
+    let mut loss_sum = 0.0;
+    let mut seen = 0i64;
+
+    for (x, y) in Iter2::new(images, labels, batch_size)
+        .shuffle()
+        .to_device(device)
+        .return_smaller_last_batch()
+    {
+        let x = augment.apply(&x);
+        opt.set_lr(scheduler.current_lr());
+
+        let n = y.size()[0];
+        let loss = batch_loss(&model.forward(&x), &y, class_weights, n);
+        opt.backward_step(&loss);
+        scheduler.step();
+
+        loss_sum += loss.double_value(&[]) * n as f64;
+        seen += n;
+    }
+
+    if seen == 0 { 0.0 } else { loss_sum / seen as f64 }
+}
+
+// Samples mixed into every batch, e.g. MNIST while fine-tuning on mydata
+pub struct ReplaySet<'a> {
+    pub images: &'a Tensor,
+    pub labels: &'a Tensor,
+    // share of each batch taken from the replay set
+    pub ratio: f64,
+}
+
+// Like train_epoch, but every batch is topped up with random samples from `replay`
+#[allow(clippy::too_many_arguments)]
+pub fn train_epoch_with_replay(
+    model: &impl Module,
+    opt: &mut Optimizer,
+    scheduler: &mut LrScheduler,
+    augment: &Augmenter,
+    images: &Tensor,
+    labels: &Tensor,
+    class_weights: Option<&Tensor>,
+    replay: &ReplaySet,
+    batch_size: i64,
+    device: Device,
+) -> f64 {
+    let (own_batch, replay_batch) = split_batch(batch_size, replay.ratio);
+    let replay_n = replay.labels.size()[0];
+    if replay_batch == 0 || replay_n == 0 {
+        return train_epoch(model, opt, scheduler, augment, images, labels, class_weights, batch_size, device);
+    }
+
+    let mut loss_sum = 0.0;
+    let mut seen = 0i64;
+
+    for (x, y) in Iter2::new(images, labels, own_batch)
+        .shuffle()
+        .return_smaller_last_batch()
+    {
+        let own = y.size()[0];
+        let idx = Tensor::randint(replay_n, [replay_batch], (Kind::Int64, Device::Cpu));
+        let x = Tensor::cat(&[x, replay.images.index_select(0, &idx)], 0).to_device(device);
+        let y = Tensor::cat(&[y, replay.labels.index_select(0, &idx)], 0).to_device(device);
+        let x = augment.apply(&x);
+
+        opt.set_lr(scheduler.current_lr());
+
+        let loss = batch_loss(&model.forward(&x), &y, class_weights, own);
+        opt.backward_step(&loss);
+        scheduler.step();
+
+        let n = y.size()[0];
+        loss_sum += loss.double_value(&[]) * n as f64;
+        seen += n;
+    }
+
+    if seen == 0 { 0.0 } else { loss_sum / seen as f64 }
+}
+
+// Mean cross-entropy. With `class_weights` the first `own` samples count with the weight of their
+// class and the rest (replayed samples) with 1.
+fn batch_loss(logits: &Tensor, y: &Tensor, class_weights: Option<&Tensor>, own: i64) -> Tensor {
+    let Some(weights) = class_weights else {
+        return logits.cross_entropy_for_logits(y);
+    };
+
+    let n = y.size()[0];
+    let per_sample = -logits.log_softmax(-1, Kind::Float).gather(1, &y.unsqueeze(1), false).squeeze_dim(1);
+    let own_weights = weights.to_device(y.device()).index_select(0, &y.narrow(0, 0, own));
+    let w = Tensor::cat(&[own_weights, Tensor::ones([n - own], (Kind::Float, y.device()))], 0);
+    (per_sample * &w).sum(Kind::Float) / w.sum(Kind::Float)
+}
+
+// (own samples, replay samples) per batch; at least one own sample is always kept
+pub fn split_batch(batch_size: i64, replay_ratio: f64) -> (i64, i64) {
+    let replay = ((batch_size as f64) * replay_ratio.clamp(0.0, 1.0)).round() as i64;
+    let replay = replay.min(batch_size - 1).max(0);
+    (batch_size - replay, replay)
+}
+
+// Number of batches train_epoch runs for `n` samples (the last, smaller batch included)
+pub fn steps_per_epoch(n: i64, batch_size: i64) -> usize {
+    ((n + batch_size - 1) / batch_size.max(1)) as usize
+}
+
+// Carves a random `fraction` of the set out as validation data: ((train_x, train_y), (val_x, val_y))
+pub fn split_validation(images: &Tensor, labels: &Tensor, fraction: f64) -> ((Tensor, Tensor), (Tensor, Tensor)) {
+    let n = images.size()[0];
+    let n_val = ((n as f64) * fraction.clamp(0.0, 1.0)).round() as i64;
+
+    let perm = Tensor::randperm(n, (Kind::Int64, images.device()));
+    let val_idx = perm.narrow(0, 0, n_val);
+    let train_idx = perm.narrow(0, n_val, n - n_val);
+
+    (
+        (images.index_select(0, &train_idx), labels.index_select(0, &train_idx)),
+        (images.index_select(0, &val_idx), labels.index_select(0, &val_idx)),
+    )
+}
@@ -1 +1,18 @@
-﻿
\ No newline at end of file
+﻿pub mod f_training;
+pub mod c_early_stopping;
+pub mod c_optimizer;
+pub mod c_lr_scheduler;
+pub mod f_mydata;
+pub mod f_finetune;
+pub mod c_cli_args;
+pub mod f_train_base;
+pub mod f_eval;
+pub mod f_export;
+pub mod c_training_state;
+pub mod c_augmenter;
+pub mod c_eval_report;
+pub mod c_run_dir;
+pub mod f_dataset;
+pub mod f_mydata_idx;
+pub mod f_mydata_lint;
+pub mod f_idx;
\ No newline at end of file